@@ -0,0 +1,124 @@
+use libmpv2::events::Event;
+use libmpv2::{Format, Mpv};
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+use crate::frame_dump::wait_for;
+use crate::{Error, Result};
+
+/// Accepted benchmark lengths, in seconds of video.
+const DURATION_RANGE: std::ops::RangeInclusive<f64> = 1.0..=600.0;
+
+/// Longest a benchmark may take in wall time before it's given up on.
+const BENCHMARK_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// How often progress is reported.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BenchmarkProgress {
+    /// Seconds of video decoded so far.
+    pub position: f64,
+    pub duration: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BenchmarkResult {
+    /// The decoder actually used (`hwdec-current`), `no` for software.
+    pub hwdec: String,
+    /// Frames decoded, estimated from the position and frame rate
+    /// (`estimated-frame-number`); mpv doesn't count them directly.
+    pub frames: i64,
+    /// Frames dropped by the decoder or the VO.
+    pub dropped: i64,
+    /// Seconds of video decoded, less than asked if the file is shorter.
+    pub decoded_secs: f64,
+    pub elapsed_ms: u64,
+    /// `frames` per second of wall time.
+    pub fps: f64,
+}
+
+/// Decodes the first `duration` seconds of `path`'s video as fast as it
+/// goes (`untimed`, with `vo=null` and no audio or subtitles) using `hwdec`,
+/// calling `progress` a few times a second, and reports the throughput.
+///
+/// This runs its own mpv, so the player isn't disturbed, though the two do
+/// compete for the CPU and GPU. Call it off the render thread; it blocks
+/// until the span is decoded, or fails after 10 minutes.
+pub fn benchmark_decode(
+    path: &str,
+    duration: f64,
+    hwdec: &str,
+    mut progress: impl FnMut(BenchmarkProgress),
+) -> Result<BenchmarkResult> {
+    if !DURATION_RANGE.contains(&duration) {
+        return Err(Error::InvalidArgument(format!(
+            "benchmark duration {duration}s is outside 1..=600"
+        )));
+    }
+
+    let mut mpv = Mpv::with_initializer(|init| {
+        init.set_option("vo", "null")?;
+        init.set_option("ao", "null")?;
+        init.set_option("aid", "no")?;
+        init.set_option("sid", "no")?;
+        init.set_option("untimed", true)?;
+        init.set_option("hwdec", hwdec)?;
+        // Hold the last frame at the end, so the counters stay readable.
+        init.set_option("keep-open", "always")?;
+        Ok(())
+    })?;
+    mpv.observe_property("time-pos", Format::Double, 0)?;
+    mpv.observe_property("eof-reached", Format::Flag, 1)?;
+    mpv.command("loadfile", &[path, "replace"])?;
+    wait_for(&mut mpv, "the file to open", |event| {
+        matches!(event, Event::FileLoaded)
+    })?;
+    // `vid` stays `auto` either way; only the selected track tells.
+    if mpv.get_property::<i64>("current-tracks/video/id").is_err() {
+        return Err(Error::InvalidArgument(format!("{path} has no video")));
+    }
+
+    let started = Instant::now();
+    let mut reported = started;
+    let position = loop {
+        if started.elapsed() >= BENCHMARK_TIMEOUT {
+            return Err(Error::Timeout("the decode benchmark".into()));
+        }
+        match mpv.wait_event(PROGRESS_INTERVAL.as_secs_f64()) {
+            Some(Ok(Event::EndFile(_))) => {
+                return Err(Error::Mpv("the file ended unexpectedly".into()))
+            }
+            Some(Err(e)) => return Err(e.into()),
+            _ => {}
+        }
+
+        let position = mpv.get_property("time-pos").unwrap_or(0.0);
+        if position >= duration || mpv.get_property("eof-reached").unwrap_or(false) {
+            break position.min(duration);
+        }
+        if reported.elapsed() >= PROGRESS_INTERVAL {
+            reported = Instant::now();
+            progress(BenchmarkProgress { position, duration });
+        }
+    };
+    let elapsed = started.elapsed();
+    mpv.set_property("pause", true)?;
+    progress(BenchmarkProgress { position, duration });
+
+    let frames = mpv.get_property("estimated-frame-number").unwrap_or(0);
+    let dropped = mpv.get_property("decoder-frame-drop-count").unwrap_or(0)
+        + mpv.get_property("frame-drop-count").unwrap_or(0);
+    Ok(BenchmarkResult {
+        hwdec: mpv
+            .get_property("hwdec-current")
+            .unwrap_or_else(|_| "no".into()),
+        frames,
+        dropped,
+        decoded_secs: position,
+        elapsed_ms: elapsed.as_millis() as u64,
+        fps: frames as f64 / elapsed.as_secs_f64(),
+    })
+}
@@ -0,0 +1,24 @@
+pub mod audio;
+pub mod cache;
+pub mod diagnostics;
+pub mod filters;
+pub mod osd;
+pub mod playback;
+pub mod playlist;
+pub mod subtitle;
+pub mod tracks;
+pub mod video;
+pub mod window;
+
+use crate::{Error, Result};
+
+/// Runs `f` on Tauri's blocking thread pool and waits for it, for commands
+/// that block for long (a headless mpv, a synchronous mpv command) and would
+/// otherwise hold up an async runtime worker.
+pub async fn blocking<T: Send + 'static>(
+    f: impl FnOnce() -> Result<T> + Send + 'static,
+) -> Result<T> {
+    tauri::async_runtime::spawn_blocking(f)
+        .await
+        .map_err(|err| Error::Task(err.to_string()))?
+}
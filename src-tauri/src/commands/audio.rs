@@ -0,0 +1,455 @@
+use serde::Serialize;
+use std::f64::consts::SQRT_2;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+use tauri::{Emitter, State, WebviewWindow};
+
+use crate::commands::{blocking, filters, tracks};
+use crate::player::{Player, PlayerHandle};
+use crate::waveform;
+use crate::{Error, Result};
+
+const REPLAYGAIN_MODES: &[&str] = &["no", "track", "album"];
+
+/// Volume step interval of `switch_audio_device_with_fade`.
+const FADE_STEP: Duration = Duration::from_millis(20);
+
+/// Centres of the octave bands `set_visualizer` measures, in Hz. The first
+/// band also takes in everything below it and the last everything above.
+const VISUALIZER_BANDS: &[f64] = &[
+    31.25, 62.5, 125.0, 250.0, 500.0, 1000.0, 2000.0, 4000.0, 8000.0,
+];
+
+/// Accepted `set_visualizer` event intervals, in ms.
+const VISUALIZER_INTERVALS: std::ops::RangeInclusive<u64> = 10..=1000;
+
+const DEVICE_LOST_MODES: &[&str] = &["pause", "switch-to-default", "continue"];
+
+/// Payload of `mpv-device-lost`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceLost {
+    /// The `audio-device` that went away.
+    pub device: String,
+    /// The `set_on_device_lost` mode that was applied.
+    pub action: String,
+}
+
+/// Payload of `mpv-visualizer`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VisualizerFrame {
+    /// Centre of each band, in Hz, low to high.
+    pub frequencies: &'static [f64],
+    /// RMS level of each band over the latest audio frame, in dBFS, or
+    /// `None` for silence.
+    pub levels: Vec<Option<f64>>,
+}
+
+impl VisualizerFrame {
+    /// Reads the visualizer's filter metadata, which is unavailable until
+    /// audio has gone through it.
+    fn read(player: &Player) -> Option<Self> {
+        let metadata = player.get_json("af-metadata/visualizer").ok()?;
+        // Each band is measured as a channel of its own.
+        let levels = (1..=VISUALIZER_BANDS.len())
+            .map(|band| {
+                let level = &metadata[&*format!("lavfi.astats.{band}.RMS_level")];
+                level
+                    .as_str()?
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|level| level.is_finite())
+            })
+            .collect();
+        Some(Self {
+            frequencies: VISUALIZER_BANDS,
+            levels,
+        })
+    }
+}
+
+/// The filter `set_visualizer` adds to the `af` chain, for `channels`
+/// channels of audio. A mono copy of the audio is split into the
+/// `VISUALIZER_BANDS` and merged back as one channel per band, so a single
+/// `astats` measures every band. The band channels are then dropped again,
+/// leaving the audio as it was and the readings on it as metadata.
+fn visualizer_filter(channels: usize) -> String {
+    let bands = VISUALIZER_BANDS.len();
+    let mut graph = format!(
+        "aformat=channel_layouts={channels}c,asplit[main][mono];\
+         [mono]aformat=channel_layouts=mono,asplit={bands}"
+    );
+    for band in 0..bands {
+        write!(graph, "[split{band}]").ok();
+    }
+    for (band, centre) in VISUALIZER_BANDS.iter().enumerate() {
+        let filter = if band == 0 {
+            format!("lowpass=f={}", centre * SQRT_2)
+        } else if band == bands - 1 {
+            format!("highpass=f={}", centre / SQRT_2)
+        } else {
+            format!("bandpass=f={centre}:width_type=o:width=1")
+        };
+        write!(graph, ";[split{band}]{filter}[band{band}]").ok();
+    }
+    graph.push(';');
+    for band in 0..bands {
+        write!(graph, "[band{band}]").ok();
+    }
+    write!(
+        graph,
+        "amerge=inputs={bands},\
+         astats=metadata=1:reset=1:measure_perchannel=RMS_level:measure_overall=none[bands];\
+         [bands][main]amerge=inputs=2,pan={channels}c"
+    )
+    .ok();
+    for channel in 0..channels {
+        write!(graph, "|c{channel}=c{}", bands + channel).ok();
+    }
+    // `%len%` quoting, since the graph has brackets of its own.
+    format!("lavfi=graph=%{}%{graph}", graph.len())
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayGain {
+    pub mode: String,
+    pub preamp: f64,
+    pub fallback: f64,
+}
+
+impl ReplayGain {
+    fn read(player: &Player) -> Result<Self> {
+        Ok(Self {
+            mode: player.mpv.get_property("replaygain")?,
+            preamp: player.mpv.get_property("replaygain-preamp")?,
+            fallback: player.mpv.get_property("replaygain-fallback")?,
+        })
+    }
+}
+
+/// Sets the replaygain mode (`track`/`album`/`no`) and preamp in dB. Files
+/// without replaygain tags get `fallback` dB applied instead, so untagged
+/// tracks don't jump out of a normalized playlist.
+#[tauri::command]
+pub async fn set_replaygain(
+    player: State<'_, PlayerHandle>,
+    mode: String,
+    preamp: Option<f64>,
+    fallback: Option<f64>,
+) -> Result<ReplayGain> {
+    if !REPLAYGAIN_MODES.contains(&mode.as_str()) {
+        return Err(Error::InvalidArgument(format!(
+            "unknown replaygain mode `{mode}`"
+        )));
+    }
+
+    player.call(move |player| {
+        player.mpv.set_property("replaygain", mode)?;
+        if let Some(preamp) = preamp {
+            player.mpv.set_property("replaygain-preamp", preamp)?;
+        }
+        if let Some(fallback) = fallback {
+            player.mpv.set_property("replaygain-fallback", fallback)?;
+        }
+
+        let applied = ReplayGain::read(player)?;
+        player.emit("mpv-replaygain", applied.clone());
+        Ok(applied)
+    })
+}
+
+/// Returns the audio output driver that actually initialized (e.g. the first
+/// working entry of an `ao` fallback list), or `None` before audio starts.
+/// Changes are also emitted as `mpv-current-ao`.
+#[tauri::command]
+pub async fn get_ao_current(player: State<'_, PlayerHandle>) -> Result<Option<String>> {
+    player.call(|player| Ok(player.mpv.get_property("current-ao").ok()))
+}
+
+/// Asks for exclusive access to the audio device (`audio-exclusive`) for
+/// bit-perfect output, reloading the audio output so it applies right away.
+/// Returns the applied state.
+///
+/// Only WASAPI (Windows) and Core Audio (macOS) support this; elsewhere it
+/// logs a warning and stays off. If the device can't be opened exclusively,
+/// typically because another app is using it, shared mode is restored and
+/// the error says so.
+#[tauri::command]
+pub async fn set_audio_exclusive(player: State<'_, PlayerHandle>, enabled: bool) -> Result<bool> {
+    if !cfg!(any(windows, target_os = "macos")) {
+        println!("Exclusive audio isn't supported on this platform, ignoring");
+        return Ok(false);
+    }
+
+    player.call(move |player| {
+        let had_audio = player.mpv.get_property::<String>("current-ao").is_ok();
+        player.mpv.set_property("audio-exclusive", enabled)?;
+        player.mpv.command("ao-reload", &[])?;
+
+        if had_audio && player.mpv.get_property::<String>("current-ao").is_err() {
+            player.mpv.set_property("audio-exclusive", !enabled)?;
+            player.mpv.command("ao-reload", &[])?;
+            return Err(Error::Mpv(format!(
+                "couldn't open the audio device in {} mode; it may be busy in another app",
+                if enabled { "exclusive" } else { "shared" }
+            )));
+        }
+        Ok(enabled)
+    })
+}
+
+/// Adds an external audio track from a local path or an `http(s)` URL,
+/// selecting it if `select` is set. Download failures and timeouts
+/// (`network-timeout`) come back as errors naming the source.
+#[tauri::command]
+pub async fn add_audio(
+    player: State<'_, PlayerHandle>,
+    source: String,
+    select: bool,
+) -> Result<()> {
+    tracks::check_external_track(&source)?;
+    player.call(move |player| tracks::add_external_track(player, "audio-add", &source, select))
+}
+
+/// Returns the peak amplitude (0 to 1) of each of `buckets` equal slices of
+/// `path`'s audio, for drawing a waveform under the scrubber, emitting
+/// `mpv-waveform-progress` while it decodes. Files without audio give an
+/// empty list. The audio is decoded by a separate headless mpv, so playback
+/// isn't touched, and results are cached per path for the session.
+#[tauri::command]
+pub async fn generate_waveform(
+    window: WebviewWindow,
+    path: String,
+    buckets: u32,
+) -> Result<Vec<f32>> {
+    blocking(move || {
+        waveform::generate_waveform(&path, buckets, |progress| {
+            window.emit("mpv-waveform-progress", progress).ok();
+        })
+    })
+    .await
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AvDelayCompensation {
+    /// The compensation in effect, in milliseconds.
+    pub compensation_ms: u32,
+    /// Resulting `audio-delay` in seconds: the compensation plus any delay
+    /// set on top of it.
+    pub audio_delay: f64,
+}
+
+/// Delays audio by `ms` (0 to 2000) to match a display that shows video
+/// late, like a projector fed through `mirror_to_window`. Any `audio-delay`
+/// tweak made on top is kept; only the previous compensation is swapped for
+/// the new one. There's one audio stream, so with a mirror the main window
+/// ends up ahead by the same amount. Returns the applied values.
+#[tauri::command]
+pub async fn set_av_delay_compensation(
+    player: State<'_, PlayerHandle>,
+    ms: u32,
+) -> Result<AvDelayCompensation> {
+    if ms > 2000 {
+        return Err(Error::InvalidArgument(format!(
+            "delay compensation {ms}ms is outside 0..=2000"
+        )));
+    }
+
+    player.call(move |player| {
+        let delay: f64 = player.mpv.get_property("audio-delay")?;
+        let delta = (ms as f64 - player.av_delay_compensation_ms as f64) / 1000.0;
+        player.mpv.set_property("audio-delay", delay + delta)?;
+        player.av_delay_compensation_ms = ms;
+        Ok(AvDelayCompensation {
+            compensation_ms: ms,
+            audio_delay: player.mpv.get_property("audio-delay")?,
+        })
+    })
+}
+
+fn switch_audio_device(player: &Player, name: &str) -> Result<()> {
+    player.mpv.set_property("audio-device", name)?;
+    player.emit("mpv-audio-device", name);
+    Ok(())
+}
+
+/// Switches to the audio device `name` (as listed in `audio-device-list`)
+/// without a pop: the volume fades out over `fade_ms` (up to 5000), the
+/// device changes, `mpv-audio-device` is emitted, and the volume fades back
+/// in to exactly where it was. Returns once the fade has started. Another
+/// call mid-fade takes over and still restores the original volume.
+#[tauri::command]
+pub async fn switch_audio_device_with_fade(
+    player: State<'_, PlayerHandle>,
+    name: String,
+    fade_ms: u64,
+) -> Result<()> {
+    if fade_ms > 5000 {
+        return Err(Error::InvalidArgument(format!(
+            "fade {fade_ms}ms is outside 0..=5000"
+        )));
+    }
+
+    player.call(move |player| {
+        let devices = player.get_json("audio-device-list")?;
+        let known = devices
+            .as_array()
+            .is_some_and(|devices| devices.iter().any(|device| device["name"] == name.as_str()));
+        if !known {
+            return Err(Error::InvalidArgument(format!(
+                "no audio device named `{name}`"
+            )));
+        }
+
+        player.cancel_timer("audio-fade");
+        let volume = match player.audio_fade_volume {
+            Some(volume) => volume,
+            None => player.mpv.get_property("volume")?,
+        };
+        if fade_ms == 0 {
+            player.audio_fade_volume = None;
+            player.mpv.set_property("volume", volume)?;
+            return switch_audio_device(player, &name);
+        }
+
+        player.audio_fade_volume = Some(volume);
+        let start: f64 = player.mpv.get_property("volume")?;
+        let fade = Duration::from_millis(fade_ms);
+        let mut started = Instant::now();
+        let mut switched = false;
+        player.set_timer("audio-fade", FADE_STEP, move |player| {
+            let t = (started.elapsed().as_secs_f64() / fade.as_secs_f64()).min(1.0);
+            let level = if switched {
+                volume * t
+            } else {
+                start * (1.0 - t)
+            };
+            if let Err(e) = player.mpv.set_property("volume", level) {
+                println!("Audio fade stopped: {}", e);
+                if !switched {
+                    switch_audio_device(player, &name).ok();
+                }
+                player.mpv.set_property("volume", volume).ok();
+                player.audio_fade_volume = None;
+                return false;
+            }
+            if t < 1.0 {
+                return true;
+            }
+            if !switched {
+                if let Err(e) = switch_audio_device(player, &name) {
+                    println!("Failed to switch audio device: {}", e);
+                }
+                switched = true;
+                started = Instant::now();
+                return true;
+            }
+            player.mpv.set_property("volume", volume).ok();
+            player.audio_fade_volume = None;
+            false
+        });
+        Ok(())
+    })
+}
+
+/// Analyses the audio as it plays and emits `mpv-visualizer` every
+/// `interval_ms` (33 ms unless given), for driving a visualizer in the page.
+/// mpv doesn't hand out audio samples to take an FFT of, so each event
+/// carries the level of nine octave bands from 31 Hz to 8 kHz instead,
+/// measured by ffmpeg filters on a mono mix. Works the same for audio-only
+/// and A/V files; nothing is emitted while no audio is playing.
+///
+/// The analysis runs nine biquad filters over the mono mix on the audio
+/// thread, which is cheap next to video decoding but adds up on low-end
+/// devices, so turn it off when the visualizer isn't shown. Turning it off
+/// removes the filter again. Returns whether it's on.
+#[tauri::command]
+pub async fn set_visualizer(
+    player: State<'_, PlayerHandle>,
+    enabled: bool,
+    interval_ms: Option<u64>,
+) -> Result<bool> {
+    let interval_ms = interval_ms.unwrap_or(33);
+    if !VISUALIZER_INTERVALS.contains(&interval_ms) {
+        return Err(Error::InvalidArgument(format!(
+            "visualizer interval {interval_ms}ms is outside 10..=1000"
+        )));
+    }
+
+    player.call(move |player| {
+        if !enabled {
+            player.cancel_timer("visualizer");
+            filters::replace_labeled(player, "af", "visualizer", None)?;
+            return Ok(false);
+        }
+
+        // The audio is kept at the channel count it's played with.
+        let channels: i64 = player
+            .mpv
+            .get_property("audio-out-params/channel-count")
+            .unwrap_or(2);
+        let filter = visualizer_filter(channels.max(1) as usize);
+        filters::replace_labeled(player, "af", "visualizer", Some(&filter))?;
+        player.set_timer("visualizer", Duration::from_millis(interval_ms), |player| {
+            if let Some(frame) = VisualizerFrame::read(player) {
+                player.emit("mpv-visualizer", frame);
+            }
+            true
+        });
+        Ok(true)
+    })
+}
+
+/// Called with each new `audio-device-list`: if the selected device has
+/// dropped out of it, e.g. a USB or Bluetooth device was disconnected,
+/// applies the `set_on_device_lost` mode and emits `mpv-device-lost`. With
+/// `auto` selected there's nothing to lose, since mpv follows the system
+/// default itself.
+pub fn check_audio_device(player: &mut Player, devices: &serde_json::Value) {
+    let Ok(device) = player.mpv.get_property::<String>("audio-device") else {
+        return;
+    };
+    let present = device == "auto"
+        || devices
+            .as_array()
+            .is_some_and(|devices| devices.iter().any(|known| known["name"] == device.as_str()));
+    if present || player.audio_device_lost {
+        player.audio_device_lost = !present;
+        return;
+    }
+    player.audio_device_lost = true;
+
+    let action = player.on_device_lost.clone();
+    let applied = match action.as_str() {
+        "pause" => player.mpv.set_property("pause", true),
+        "switch-to-default" => switch_audio_device(player, "auto"),
+        _ => Ok(()),
+    };
+    if let Err(e) = applied {
+        println!("Failed to handle lost audio device: {}", e);
+    }
+    player.emit("mpv-device-lost", DeviceLost { device, action });
+}
+
+/// Chooses what happens when the selected audio device disconnects
+/// mid-playback, which can otherwise leave mpv stalled on a dead output:
+/// `pause`, `switch-to-default` (`audio-device=auto`, emitting
+/// `mpv-audio-device`) or `continue`, the default, which leaves it to mpv.
+/// Disconnects are spotted from `audio-device-list` and announced with
+/// `mpv-device-lost` whatever the mode. Returns the applied mode.
+#[tauri::command]
+pub async fn set_on_device_lost(player: State<'_, PlayerHandle>, mode: String) -> Result<String> {
+    if !DEVICE_LOST_MODES.contains(&mode.as_str()) {
+        return Err(Error::InvalidArgument(format!(
+            "unknown device-lost mode `{mode}`"
+        )));
+    }
+
+    player.call(move |player| {
+        player.on_device_lost = mode.clone();
+        Ok(mode)
+    })
+}
@@ -0,0 +1,395 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tauri::State;
+use tokio::sync::oneshot;
+
+use crate::commands::playback::reopen_with;
+use crate::player::{wait_for, Player, PlayerHandle};
+use crate::{Error, Result};
+
+/// How long `flush_cache` waits for playback to pick up again.
+const REBUFFER_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Longest `set_prebuffer` holds a file paused before playing anyway.
+const PREBUFFER_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often the prebuffer checks the cache and reports progress.
+const PREBUFFER_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Longest `set_cache_pause` lets playback wait to rebuffer, in seconds.
+const MAX_CACHE_PAUSE_WAIT: f64 = 60.0;
+
+/// What `set_low_latency` sets: mpv's `low-latency` profile, with the
+/// cache turned off as well.
+const LOW_LATENCY_PROPERTIES: &[(&str, &str)] = &[
+    ("video-latency-hacks", "yes"),
+    ("cache", "no"),
+    ("cache-pause", "no"),
+    ("audio-buffer", "0"),
+    ("vd-lavc-threads", "1"),
+    ("demuxer-lavf-probe-info", "nostreams"),
+    ("demuxer-lavf-analyzeduration", "0.1"),
+    ("video-sync", "audio"),
+    ("interpolation", "no"),
+    ("stream-buffer-size", "4k"),
+];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrebufferProgress {
+    /// Seconds buffered ahead so far.
+    pub buffered: f64,
+    pub target: f64,
+    /// Set on the last event, once playback has been released.
+    pub done: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheOpts {
+    /// Where mpv keeps its cache files; created if missing.
+    pub dir: PathBuf,
+    /// Cap on cached bytes (`demuxer-max-bytes`), or mpv's default if `None`.
+    pub max_bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VideoQueueOpts {
+    /// Decoded frames held ahead of the VO (`vd-queue-max-samples`), 1 to
+    /// 1000.
+    pub max_frames: i64,
+    /// Cap on those frames' size (`vd-queue-max-bytes`), at least 1 MiB.
+    pub max_bytes: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodeQueue {
+    pub max_frames: i64,
+    pub max_bytes: i64,
+    /// Demuxed bytes buffered ahead of the decoder right now
+    /// (`demuxer-cache-state/fw-bytes`), 0 with nothing open. mpv doesn't
+    /// report how full the decoded queue itself is.
+    pub demuxer_bytes: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CachePause {
+    pub enabled: bool,
+    pub wait_secs: f64,
+}
+
+/// Makes sure `dir` exists and that files can be created in it, so a bad
+/// path fails here rather than silently once mpv opens the next stream.
+fn check_cache_dir(dir: &Path) -> Result<()> {
+    let unusable = |e: std::io::Error| {
+        Error::InvalidArgument(format!("cache dir {} is unusable: {e}", dir.display()))
+    };
+    fs::create_dir_all(dir).map_err(unusable)?;
+    let probe = dir.join(".write-test");
+    fs::write(&probe, []).map_err(unusable)?;
+    fs::remove_file(&probe).ok();
+    Ok(())
+}
+
+/// Moves the demuxer cache to disk (`cache-on-disk`) so huge streams can be
+/// cached without holding them in RAM, or back to memory with `None`. This
+/// also turns the cache on for every source (`cache=yes`), not just network
+/// streams, until switched back. Takes effect for the next file opened.
+#[tauri::command]
+pub async fn set_cache_on_disk(
+    player: State<'_, PlayerHandle>,
+    opts: Option<CacheOpts>,
+) -> Result<Option<CacheOpts>> {
+    if let Some(opts) = &opts {
+        check_cache_dir(&opts.dir)?;
+    }
+
+    player.call(move |player| {
+        let Some(opts) = opts else {
+            player.mpv.set_property("cache-on-disk", false)?;
+            player.mpv.set_property("cache", "auto")?;
+            return Ok(None);
+        };
+
+        let dir = opts.dir.to_str().ok_or_else(|| {
+            Error::InvalidArgument(format!("cache dir {} isn't UTF-8", opts.dir.display()))
+        })?;
+        player.mpv.set_property("demuxer-cache-dir", dir)?;
+        if let Some(max_bytes) = opts.max_bytes {
+            player
+                .mpv
+                .set_property("demuxer-max-bytes", max_bytes.to_string())?;
+        }
+        player.mpv.set_property("cache", "yes")?;
+        player.mpv.set_property("cache-on-disk", true)?;
+        Ok(Some(opts))
+    })
+}
+
+/// Throws away everything buffered and reads the stream afresh
+/// (`drop-buffers`), to unstick a stalled stream without reloading it.
+/// Resolves once playback has restarted; the refill can be followed through
+/// `mpv-paused-for-cache` and `mpv-cache-buffering-state`. Returns `false`
+/// without doing anything for local files, which have nothing to refill.
+#[tauri::command]
+pub async fn flush_cache(player: State<'_, PlayerHandle>) -> Result<bool> {
+    let (restarted_tx, restarted_rx) = oneshot::channel();
+    let flushed = player.call(move |player| {
+        if !player
+            .mpv
+            .get_property("demuxer-via-network")
+            .unwrap_or(false)
+        {
+            return Ok(false);
+        }
+        player.mpv.command("drop-buffers", &[])?;
+        player.restart_waiters.push(restarted_tx);
+        Ok(true)
+    })?;
+    if !flushed {
+        return Ok(false);
+    }
+
+    wait_for(
+        restarted_rx,
+        REBUFFER_TIMEOUT,
+        "playback to resume after re-buffering",
+    )
+    .await?;
+    Ok(true)
+}
+
+/// Holds the next file paused until `secs` of it are buffered
+/// (`demuxer-cache-duration`), then plays it, for a smooth start on network
+/// streams. It is released early once the whole file is cached, and after
+/// 30 s regardless, or as soon as that file ends or another one starts.
+/// Emits `mpv-prebuffer` while waiting. Only applies to the one load after
+/// this call; `None` disarms it.
+#[tauri::command]
+pub async fn set_prebuffer(player: State<'_, PlayerHandle>, secs: Option<f64>) -> Result<()> {
+    if let Some(secs) = secs {
+        if !(secs > 0.0 && secs <= PREBUFFER_TIMEOUT.as_secs_f64()) {
+            return Err(Error::InvalidArgument(format!(
+                "prebuffer {secs}s is outside 0..=30"
+            )));
+        }
+    }
+
+    player.call(move |player| {
+        player.prebuffer = secs;
+        Ok(())
+    })
+}
+
+/// Starts a prebuffer armed by `set_prebuffer`, as a new file starts.
+pub fn start_prebuffer(player: &mut Player) {
+    let Some(target) = player.prebuffer.take() else {
+        return;
+    };
+    let was_paused = player.mpv.get_property("pause").unwrap_or(false);
+    if let Err(e) = player.mpv.set_property("pause", true) {
+        println!("Failed to pause for prebuffering: {}", e);
+        return;
+    }
+
+    player.prebuffering = Some((target, was_paused));
+    let started = Instant::now();
+    player.set_timer("prebuffer", PREBUFFER_POLL_INTERVAL, move |player| {
+        // Both are unavailable until the demuxer is up.
+        let buffered = player
+            .mpv
+            .get_property("demuxer-cache-duration")
+            .unwrap_or(0.0);
+        let fully_cached = player
+            .mpv
+            .get_property("demuxer-cache-idle")
+            .unwrap_or(false)
+            && buffered > 0.0;
+        if buffered >= target || fully_cached || started.elapsed() >= PREBUFFER_TIMEOUT {
+            finish_prebuffer(player, buffered);
+            return false;
+        }
+
+        player.emit(
+            "mpv-prebuffer",
+            PrebufferProgress {
+                buffered,
+                target,
+                done: false,
+            },
+        );
+        true
+    });
+}
+
+/// Ends a running prebuffer as its file ends or the next one starts, so it
+/// can't go on to unpause a file it wasn't started for.
+pub fn cancel_prebuffer(player: &mut Player) {
+    if player.prebuffering.is_none() {
+        return;
+    }
+    player.cancel_timer("prebuffer");
+    let buffered = player
+        .mpv
+        .get_property("demuxer-cache-duration")
+        .unwrap_or(0.0);
+    finish_prebuffer(player, buffered);
+}
+
+/// Sends the last `mpv-prebuffer` and puts back the pause state from before.
+fn finish_prebuffer(player: &mut Player, buffered: f64) {
+    let Some((target, was_paused)) = player.prebuffering.take() else {
+        return;
+    };
+    player.emit(
+        "mpv-prebuffer",
+        PrebufferProgress {
+            buffered,
+            target,
+            done: true,
+        },
+    );
+    player.mpv.set_property("pause", was_paused).ok();
+}
+
+/// Caps the decoder's output queue (`vd-queue-enable` with
+/// `vd-queue-max-samples` and `vd-queue-max-bytes`), so low-RAM devices can
+/// trade a little latency for memory. The queue is only set up when the
+/// decoder opens, so the current file is reopened and continues from the same
+/// position. Returns the applied limits and current demuxer occupancy.
+#[tauri::command]
+pub async fn set_decode_queue(
+    player: State<'_, PlayerHandle>,
+    opts: VideoQueueOpts,
+) -> Result<DecodeQueue> {
+    if !(1..=1000).contains(&opts.max_frames) {
+        return Err(Error::InvalidArgument(format!(
+            "queue length {} is outside 1..=1000",
+            opts.max_frames
+        )));
+    }
+    if opts.max_bytes < 1 << 20 {
+        return Err(Error::InvalidArgument(format!(
+            "queue size {} bytes is below 1 MiB",
+            opts.max_bytes
+        )));
+    }
+
+    reopen_with(&player, move |player| {
+        player.mpv.set_property("vd-queue-enable", true)?;
+        player
+            .mpv
+            .set_property("vd-queue-max-samples", opts.max_frames)?;
+        player
+            .mpv
+            .set_property("vd-queue-max-bytes", opts.max_bytes.to_string())?;
+        Ok(())
+    })
+    .await?;
+
+    player.call(|player| {
+        Ok(DecodeQueue {
+            max_frames: player.mpv.get_property("vd-queue-max-samples")?,
+            max_bytes: player.mpv.get_property("vd-queue-max-bytes")?,
+            demuxer_bytes: player
+                .mpv
+                .get_property("demuxer-cache-state/fw-bytes")
+                .unwrap_or(0),
+        })
+    })
+}
+
+/// Trims buffering everywhere it can be trimmed, for near-realtime streams
+/// where delay matters more than smoothness: no cache, no audio buffer, a
+/// single decoder thread, minimal stream probing and `video-latency-hacks`.
+/// The cost is stutter on any network hiccup, slower decoding of heavy
+/// video and possibly missed streams in files that need probing. Turning
+/// it off puts back the values it replaced. The demuxer settings take
+/// effect for the next file opened. Returns the values now applied.
+#[tauri::command]
+pub async fn set_low_latency(
+    player: State<'_, PlayerHandle>,
+    enabled: bool,
+) -> Result<BTreeMap<&'static str, String>> {
+    player.call(move |player| {
+        if enabled && player.low_latency_saved.is_none() {
+            let mut saved = Vec::with_capacity(LOW_LATENCY_PROPERTIES.len());
+            for (name, _) in LOW_LATENCY_PROPERTIES {
+                saved.push((*name, player.mpv.get_property::<String>(name)?));
+            }
+            for (i, (name, value)) in LOW_LATENCY_PROPERTIES.iter().enumerate() {
+                if let Err(err) = player.mpv.set_property(name, *value) {
+                    // Put back what was already changed, so a later call
+                    // doesn't take the half-applied values as the originals.
+                    for (name, value) in saved[..i].iter().rev() {
+                        if let Err(restore) = player.mpv.set_property(name, value.as_str()) {
+                            println!("Failed to restore {}: {}", name, Error::from(restore));
+                        }
+                    }
+                    return Err(err.into());
+                }
+            }
+            player.low_latency_saved = Some(saved);
+        } else if !enabled {
+            if let Some(saved) = player.low_latency_saved.take() {
+                // Put back everything that can be, and keep what couldn't so
+                // turning it off again retries those.
+                let mut failed = Vec::new();
+                let mut first_err = None;
+                for (name, value) in saved {
+                    if let Err(e) = player.mpv.set_property(name, value.as_str()) {
+                        let e = Error::from(e);
+                        println!("Failed to restore {}: {}", name, e);
+                        first_err.get_or_insert(e);
+                        failed.push((name, value));
+                    }
+                }
+                if let Some(e) = first_err {
+                    player.low_latency_saved = Some(failed);
+                    return Err(e);
+                }
+            }
+        }
+
+        let mut applied = BTreeMap::new();
+        for (name, _) in LOW_LATENCY_PROPERTIES {
+            applied.insert(*name, player.mpv.get_property(name)?);
+        }
+        Ok(applied)
+    })
+}
+
+/// Chooses whether playback pauses to rebuffer when the cache runs dry
+/// (`cache-pause`), and if so how many seconds have to be buffered before
+/// it resumes (`cache-pause-wait`, 0 to 60, mpv's default is 1). A longer
+/// wait means fewer, longer stalls on a flaky connection; off, playback
+/// never waits and stutters through underruns instead. Stalls show up as
+/// `mpv-paused-for-cache` and `mpv-cache-buffering-state`. `set_low_latency`
+/// turns the pause off while it's on. Returns the applied values.
+#[tauri::command]
+pub async fn set_cache_pause(
+    player: State<'_, PlayerHandle>,
+    enabled: bool,
+    wait_secs: f64,
+) -> Result<CachePause> {
+    if !(0.0..=MAX_CACHE_PAUSE_WAIT).contains(&wait_secs) {
+        return Err(Error::InvalidArgument(format!(
+            "cache pause wait {wait_secs}s is outside 0..=60"
+        )));
+    }
+
+    player.call(move |player| {
+        player.mpv.set_property("cache-pause", enabled)?;
+        player.mpv.set_property("cache-pause-wait", wait_secs)?;
+        Ok(CachePause {
+            enabled: player.mpv.get_property("cache-pause")?,
+            wait_secs: player.mpv.get_property("cache-pause-wait")?,
+        })
+    })
+}
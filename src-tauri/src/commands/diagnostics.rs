@@ -0,0 +1,415 @@
+use glow::HasContext;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::path::PathBuf;
+use std::time::Duration;
+use tauri::State;
+
+use crate::player::{Player, PlayerHandle};
+use crate::{Error, Result};
+
+// From GL_NVX_gpu_memory_info and GL_ATI_meminfo; glow doesn't define them.
+const GPU_MEMORY_INFO_TOTAL_AVAILABLE_MEMORY_NVX: u32 = 0x9048;
+const GPU_MEMORY_INFO_CURRENT_AVAILABLE_VIDMEM_NVX: u32 = 0x9049;
+const TEXTURE_FREE_MEMORY_ATI: u32 = 0x87FC;
+
+/// Settings `dump_all_properties` captures and `apply_property_dump` writes
+/// back, in the order they're applied.
+const RESTORABLE_PROPERTIES: &[&str] = &[
+    "hwdec",
+    "vid",
+    "aid",
+    "sid",
+    "vf",
+    "af",
+    "audio-device",
+    "pause",
+    "speed",
+    "volume",
+    "mute",
+    "audio-delay",
+    "sub-delay",
+    "sub-visibility",
+    "sub-scale",
+    "sub-pos",
+    "video-sync",
+    "interpolation",
+    "framedrop",
+    "hr-seek",
+    "deinterlace",
+    "deband",
+    "brightness",
+    "contrast",
+    "saturation",
+    "gamma",
+    "hue",
+    "video-aspect-override",
+    "video-zoom",
+    "video-pan-x",
+    "video-pan-y",
+    "video-rotate",
+    "loop-file",
+    "loop-playlist",
+    "ab-loop-a",
+    "ab-loop-b",
+    "keep-open",
+    "cache",
+    "demuxer-max-bytes",
+    "demuxer-readahead-secs",
+];
+
+/// Read-only state `dump_all_properties` adds for context.
+const INFO_PROPERTIES: &[&str] = &[
+    "mpv-version",
+    "ffmpeg-version",
+    "path",
+    "file-format",
+    "duration",
+    "time-pos",
+    "current-vo",
+    "current-ao",
+    "hwdec-current",
+    "video-codec",
+    "audio-codec-name",
+    "width",
+    "height",
+    "container-fps",
+    "estimated-vf-fps",
+    "display-fps",
+    "avsync",
+    "frame-drop-count",
+    "decoder-frame-drop-count",
+    "vo-delayed-frame-count",
+    "demuxer-via-network",
+    "cache-speed",
+];
+
+/// Node-valued state, dumped as nested JSON rather than strings.
+const INFO_NODE_PROPERTIES: &[&str] = &[
+    "track-list",
+    "video-params",
+    "audio-params",
+    "demuxer-cache-state",
+];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PropertyRestore {
+    pub applied: Vec<String>,
+    /// `name: reason` for each property mpv refused.
+    pub failed: Vec<String>,
+    /// Keys that aren't restorable, or were `null` in the dump.
+    pub ignored: Vec<String>,
+}
+
+/// Where mpv keeps its files. `None` is mpv's per-user default.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MpvDirs {
+    pub config_dir: Option<PathBuf>,
+    pub cache_dir: Option<PathBuf>,
+    pub watch_later_dir: Option<PathBuf>,
+}
+
+/// Running min/avg/max of a latency, kept on the render thread.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LatencyTracker {
+    count: u64,
+    total: Duration,
+    min: Duration,
+    max: Duration,
+}
+
+impl LatencyTracker {
+    pub fn record(&mut self, latency: Duration) {
+        self.min = if self.count == 0 {
+            latency
+        } else {
+            self.min.min(latency)
+        };
+        self.max = self.max.max(latency);
+        self.total += latency;
+        self.count += 1;
+    }
+
+    fn stats(&self) -> LatencyStats {
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        LatencyStats {
+            count: self.count,
+            min_ms: ms(self.min),
+            avg_ms: ms(self.total) / self.count.max(1) as f64,
+            max_ms: ms(self.max),
+        }
+    }
+}
+
+/// Timing of the calls commands make to the render thread, see
+/// `PlayerHandle::call`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CallLatency {
+    /// From a command sending its call until the render thread picks it up.
+    pub queue: LatencyTracker,
+    /// From then until the call returns, mpv round trips included.
+    pub handling: LatencyTracker,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyStats {
+    /// Calls measured; the times are 0 until there's one.
+    pub count: u64,
+    pub min_ms: f64,
+    pub avg_ms: f64,
+    pub max_ms: f64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventLatencyStats {
+    pub queue: LatencyStats,
+    pub handling: LatencyStats,
+}
+
+impl EventLatencyStats {
+    fn read(player: &Player) -> Self {
+        Self {
+            queue: player.call_latency.queue.stats(),
+            handling: player.call_latency.handling.stats(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceUsage {
+    /// Bytes held by the demuxer packet cache.
+    pub cache_bytes: Option<u64>,
+    /// Free video memory in KiB, on drivers that report it.
+    pub gpu_free_kib: Option<u64>,
+    /// Total video memory in KiB, on drivers that report it.
+    pub gpu_total_kib: Option<u64>,
+    /// Resident set size of the whole process (Linux only).
+    pub rss_bytes: Option<u64>,
+}
+
+impl ResourceUsage {
+    fn read(player: &Player) -> Self {
+        let cache_bytes = player
+            .get_json("demuxer-cache-state")
+            .ok()
+            .and_then(|state| state["total-bytes"].as_u64());
+        let (gpu_free_kib, gpu_total_kib) = gpu_memory(&player.gl);
+
+        Self {
+            cache_bytes,
+            gpu_free_kib,
+            gpu_total_kib,
+            rss_bytes: process_rss(),
+        }
+    }
+}
+
+fn gpu_memory(gl: &glow::Context) -> (Option<u64>, Option<u64>) {
+    let extensions = gl.supported_extensions();
+    unsafe {
+        if extensions.contains("GL_NVX_gpu_memory_info") {
+            let free = gl.get_parameter_i32(GPU_MEMORY_INFO_CURRENT_AVAILABLE_VIDMEM_NVX);
+            let total = gl.get_parameter_i32(GPU_MEMORY_INFO_TOTAL_AVAILABLE_MEMORY_NVX);
+            (Some(free as u64), Some(total as u64))
+        } else if extensions.contains("GL_ATI_meminfo") {
+            // The first of the four values is the total free memory in the pool.
+            let mut info = [0; 4];
+            gl.get_parameter_i32_slice(TEXTURE_FREE_MEMORY_ATI, &mut info);
+            (Some(info[0] as u64), None)
+        } else {
+            (None, None)
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn process_rss() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn process_rss() -> Option<u64> {
+    None
+}
+
+/// Reports cache, GPU and process memory. Fields the platform or driver
+/// can't provide are `null`.
+#[tauri::command]
+pub async fn get_resource_usage(player: State<'_, PlayerHandle>) -> Result<ResourceUsage> {
+    player.call(|player| Ok(ResourceUsage::read(player)))
+}
+
+/// Emits `mpv-resource-usage` every `interval_ms`, or stops when it's 0.
+#[tauri::command]
+pub async fn set_resource_usage_interval(
+    player: State<'_, PlayerHandle>,
+    interval_ms: u64,
+) -> Result<()> {
+    player.call(move |player| {
+        if interval_ms == 0 {
+            player.cancel_timer("resource-usage");
+        } else {
+            player.set_timer(
+                "resource-usage",
+                Duration::from_millis(interval_ms),
+                |player| {
+                    player.emit("mpv-resource-usage", ResourceUsage::read(player));
+                    true
+                },
+            );
+        }
+        Ok(())
+    })
+}
+
+/// Reads the last A/V difference in seconds (`avsync`): positive when audio
+/// is ahead of video, negative when it lags behind. It's the `A-V` figure
+/// in the stats overlay. `None` unless both audio and video are playing.
+#[tauri::command]
+pub async fn get_av_sync(player: State<'_, PlayerHandle>) -> Result<Option<f64>> {
+    player.call(|player| Ok(player.mpv.get_property("avsync").ok()))
+}
+
+/// Emits `mpv-avsync` with the `get_av_sync` value every `interval_ms`, or
+/// stops when it's 0.
+#[tauri::command]
+pub async fn set_av_sync_interval(player: State<'_, PlayerHandle>, interval_ms: u64) -> Result<()> {
+    player.call(move |player| {
+        if interval_ms == 0 {
+            player.cancel_timer("avsync");
+        } else {
+            player.set_timer("avsync", Duration::from_millis(interval_ms), |player| {
+                let avsync: Option<f64> = player.mpv.get_property("avsync").ok();
+                player.emit("mpv-avsync", avsync);
+                true
+            });
+        }
+        Ok(())
+    })
+}
+
+/// Reports how long commands take to get through to mpv, for telling input
+/// lag in the player apart from lag in the page: `queue` is how long each
+/// call waited on the channel to the render thread, which grows while it's
+/// busy rendering, and `handling` how long it then took, including mpv
+/// acknowledging the properties and commands it was given. IPC from the page
+/// and the reply back come on top. Covers every call since startup.
+#[tauri::command]
+pub async fn get_event_latency_stats(player: State<'_, PlayerHandle>) -> Result<EventLatencyStats> {
+    player.call(|player| Ok(EventLatencyStats::read(player)))
+}
+
+/// Emits `mpv-event-latency` with the `get_event_latency_stats` value every
+/// `interval_ms`, or stops when it's 0.
+#[tauri::command]
+pub async fn set_event_latency_interval(
+    player: State<'_, PlayerHandle>,
+    interval_ms: u64,
+) -> Result<()> {
+    player.call(move |player| {
+        if interval_ms == 0 {
+            player.cancel_timer("event-latency");
+        } else {
+            player.set_timer(
+                "event-latency",
+                Duration::from_millis(interval_ms),
+                |player| {
+                    player.emit("mpv-event-latency", EventLatencyStats::read(player));
+                    true
+                },
+            );
+        }
+        Ok(())
+    })
+}
+
+/// Captures the player's state for bug reports: the settings
+/// `apply_property_dump` can restore plus read-only context such as the
+/// current file, decoders, outputs and track list, as one JSON object keyed
+/// by property name. Scalars are mpv's string forms. Properties that can't
+/// be read right now (nothing loaded, no audio, ...) are `null`. Large
+/// listings like `property-list`, `options` or `metadata` are left out.
+#[tauri::command]
+pub async fn dump_all_properties(player: State<'_, PlayerHandle>) -> Result<Value> {
+    player.call(|player| {
+        let mut dump = Map::new();
+        for &name in RESTORABLE_PROPERTIES.iter().chain(INFO_PROPERTIES) {
+            let value: Option<String> = player.mpv.get_property(name).ok();
+            dump.insert(name.into(), value.map_or(Value::Null, Value::String));
+        }
+        for &name in INFO_NODE_PROPERTIES {
+            dump.insert(name.into(), player.get_json(name).unwrap_or(Value::Null));
+        }
+        Ok(Value::Object(dump))
+    })
+}
+
+/// Writes the settings from a `dump_all_properties` object back, to
+/// reproduce a reported setup. Read-only and unknown keys are ignored, and a
+/// property mpv refuses doesn't stop the rest from being applied.
+#[tauri::command]
+pub async fn apply_property_dump(
+    player: State<'_, PlayerHandle>,
+    dump: Value,
+) -> Result<PropertyRestore> {
+    let Value::Object(dump) = dump else {
+        return Err(Error::InvalidArgument(
+            "a property dump has to be a JSON object".into(),
+        ));
+    };
+
+    player.call(move |player| {
+        let mut restore = PropertyRestore {
+            applied: Vec::new(),
+            failed: Vec::new(),
+            ignored: Vec::new(),
+        };
+        for &name in RESTORABLE_PROPERTIES {
+            let value = match dump.get(name) {
+                Some(Value::String(value)) => value.clone(),
+                Some(Value::Bool(value)) => if *value { "yes" } else { "no" }.into(),
+                Some(Value::Number(value)) => value.to_string(),
+                Some(_) => {
+                    restore.ignored.push(name.into());
+                    continue;
+                }
+                None => continue,
+            };
+            match player.mpv.set_property(name, value) {
+                Ok(()) => restore.applied.push(name.into()),
+                Err(e) => restore.failed.push(format!("{name}: {}", Error::from(e))),
+            }
+        }
+        restore.ignored.extend(
+            dump.keys()
+                .filter(|key| !RESTORABLE_PROPERTIES.contains(&key.as_str()))
+                .cloned(),
+        );
+        Ok(restore)
+    })
+}
+
+/// Reports the `configDir`, `cacheDir` and `watchLaterDir` startup options
+/// as applied: absolute paths, or `null` when unset or unusable.
+#[tauri::command]
+pub async fn get_dirs(player: State<'_, PlayerHandle>) -> Result<MpvDirs> {
+    player.call(|player| {
+        let dir = |name: &str| {
+            let dir: String = player.mpv.get_property(name).unwrap_or_default();
+            (!dir.is_empty()).then(|| PathBuf::from(dir))
+        };
+        Ok(MpvDirs {
+            config_dir: dir("config-dir"),
+            cache_dir: dir("cache-dir"),
+            watch_later_dir: dir("watch-later-dir"),
+        })
+    })
+}
@@ -0,0 +1,120 @@
+use tauri::State;
+
+use crate::player::{Player, PlayerHandle};
+use crate::{Error, Result};
+
+/// Replaces the filter carrying `label` in the `vf` or `af` chain, or just
+/// removes it when `filter` is `None`. Labelled entries let each feature own
+/// its filter without clobbering anything else in the chain. If the new
+/// filter can't be added, the chain is put back as it was, old filter
+/// included.
+pub fn replace_labeled(
+    player: &Player,
+    chain: &str,
+    label: &str,
+    filter: Option<&str>,
+) -> Result<()> {
+    let label = format!("@{label}");
+    let previous: String = player.mpv.get_property(chain)?;
+    // Removing a label that isn't in the chain is harmless, so don't fail on it.
+    player.mpv.command(chain, &["remove", &label]).ok();
+    if let Some(filter) = filter {
+        if let Err(e) = player
+            .mpv
+            .command(chain, &["add", &format!("{label}:{filter}")])
+        {
+            if let Err(restore) = player.mpv.set_property(chain, previous) {
+                println!("Failed to restore {}: {}", chain, Error::from(restore));
+            }
+            return Err(e.into());
+        }
+    }
+    Ok(())
+}
+
+fn check_label(label: &str) -> Result<()> {
+    let valid = !label.is_empty()
+        && label
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(Error::InvalidArgument(format!(
+            "filter label `{label}` must be non-empty and only use A-Z, a-z, 0-9, `-` or `_`"
+        )));
+    }
+    Ok(())
+}
+
+fn add(
+    player: &PlayerHandle,
+    chain: &'static str,
+    label: String,
+    filter: String,
+) -> Result<serde_json::Value> {
+    check_label(&label)?;
+    if filter.trim().is_empty() || filter.starts_with('@') {
+        return Err(Error::InvalidArgument(format!(
+            "`{filter}` is not a filter; pass the label separately"
+        )));
+    }
+
+    player.call(move |player| {
+        replace_labeled(player, chain, &label, Some(&filter))?;
+        if chain == "vf" {
+            player.redraw();
+        }
+        player.get_json(chain)
+    })
+}
+
+fn remove(player: &PlayerHandle, chain: &'static str, label: String) -> Result<serde_json::Value> {
+    check_label(&label)?;
+    player.call(move |player| {
+        replace_labeled(player, chain, &label, None)?;
+        if chain == "vf" {
+            player.redraw();
+        }
+        player.get_json(chain)
+    })
+}
+
+/// Adds (or replaces) a video filter under `label`, in mpv's `--vf` syntax,
+/// e.g. `hflip` or `lavfi=[eq=contrast=1.2]`. Filters mpv can't create are
+/// rejected with mpv's error and the chain is left as it was. Returns the
+/// resulting `vf` chain.
+#[tauri::command]
+pub async fn add_vf(
+    player: State<'_, PlayerHandle>,
+    label: String,
+    filter: String,
+) -> Result<serde_json::Value> {
+    add(&player, "vf", label, filter)
+}
+
+/// Removes the video filter added under `label`. Returns the resulting chain.
+#[tauri::command]
+pub async fn remove_vf(
+    player: State<'_, PlayerHandle>,
+    label: String,
+) -> Result<serde_json::Value> {
+    remove(&player, "vf", label)
+}
+
+/// Like `add_vf`, for the audio filter chain (`af`).
+#[tauri::command]
+pub async fn add_af(
+    player: State<'_, PlayerHandle>,
+    label: String,
+    filter: String,
+) -> Result<serde_json::Value> {
+    add(&player, "af", label, filter)
+}
+
+/// Removes the audio filter added under `label`. Returns the resulting chain.
+#[tauri::command]
+pub async fn remove_af(
+    player: State<'_, PlayerHandle>,
+    label: String,
+) -> Result<serde_json::Value> {
+    remove(&player, "af", label)
+}
@@ -0,0 +1,174 @@
+use serde::Serialize;
+use std::time::Duration;
+use tauri::State;
+
+use crate::player::{Player, PlayerHandle};
+use crate::{Error, Result};
+
+/// mpv loads scripts on their own threads; this is how long `set_osc` gives
+/// the OSC to show up before deciding it isn't available.
+const OSC_LOAD_TIMEOUT: Duration = Duration::from_secs(2);
+const OSC_LOAD_POLL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OsdBar {
+    pub enabled: bool,
+    /// Vertical position, -1 (top) to 1 (bottom).
+    pub align_y: f64,
+    /// Height in percent of the window height.
+    pub height: f64,
+}
+
+impl OsdBar {
+    fn read(player: &Player) -> Result<Self> {
+        Ok(Self {
+            enabled: player.mpv.get_property("osd-bar")?,
+            align_y: player.mpv.get_property("osd-bar-align-y")?,
+            height: player.mpv.get_property("osd-bar-h")?,
+        })
+    }
+}
+
+/// Toggles mpv's built-in stats page (what `i`/`I` show in the standalone
+/// player). It's drawn as part of the OSD by the render context, so it sits
+/// on top of the video whether or not hardware decoding is active. Returns
+/// whether the overlay is now shown.
+#[tauri::command]
+pub async fn toggle_stats_overlay(player: State<'_, PlayerHandle>) -> Result<bool> {
+    player.call(|player| {
+        player
+            .mpv
+            .command("script-binding", &["stats/display-stats-toggle"])?;
+        player.stats_overlay = !player.stats_overlay;
+        Ok(player.stats_overlay)
+    })
+}
+
+/// Shows or hides mpv's built-in on-screen controller. Like the stats page
+/// it's part of the OSD, so the render context draws it over the video. It
+/// stays visible, since mpv never sees the pointer on its own here; forward
+/// pointer input with `send_pointer` to make it usable. Fails if this mpv
+/// has no OSC (built without Lua, or `load-scripts=no`). Returns the applied
+/// state.
+#[tauri::command]
+pub async fn set_osc(player: State<'_, PlayerHandle>, enabled: bool) -> Result<bool> {
+    player.call(move |player| {
+        player.mpv.set_property("osc", enabled)?;
+        player.redraw();
+        Ok(())
+    })?;
+    if !enabled {
+        return Ok(false);
+    }
+
+    // `script-message-to` fails until a script of that name is running.
+    let mut waited = Duration::ZERO;
+    loop {
+        let loaded = player.call(|player| {
+            Ok(player
+                .mpv
+                .command("script-message-to", &["osc", "osc-visibility", "always"])
+                .is_ok())
+        })?;
+        if loaded {
+            return Ok(true);
+        }
+        if waited >= OSC_LOAD_TIMEOUT {
+            break;
+        }
+        tokio::time::sleep(OSC_LOAD_POLL).await;
+        waited += OSC_LOAD_POLL;
+    }
+
+    player.call(|player| {
+        player.mpv.set_property("osc", false)?;
+        Ok(())
+    })?;
+    Err(Error::Mpv(
+        "the OSC script isn't available in this mpv build".into(),
+    ))
+}
+
+/// Passes pointer input from the page on to mpv, for the OSC and any other
+/// script that reacts to the mouse. `x` and `y` are in physical pixels of
+/// the video surface. `action` is `down` or `up` for the left button, or
+/// `None` for a plain move.
+#[tauri::command]
+pub async fn send_pointer(
+    player: State<'_, PlayerHandle>,
+    x: i64,
+    y: i64,
+    action: Option<String>,
+) -> Result<()> {
+    let key_command = match action.as_deref() {
+        None => None,
+        Some("down") => Some("keydown"),
+        Some("up") => Some("keyup"),
+        Some(other) => {
+            return Err(Error::InvalidArgument(format!(
+                "unknown pointer action `{other}`"
+            )))
+        }
+    };
+
+    player.call(move |player| {
+        player
+            .mpv
+            .command("mouse", &[&x.to_string(), &y.to_string()])?;
+        if let Some(key_command) = key_command {
+            player.mpv.command(key_command, &["MBTN_LEFT"])?;
+        }
+        Ok(())
+    })
+}
+
+/// Turns mpv's own seek bar on or off (`osd-bar`), shown briefly on seeks
+/// and by `show_progress`, for seek feedback without any HTML. `align_y`
+/// places it from -1 (top) through 0 (centre) to 1 (bottom) and `height`
+/// sets its thickness in percent of the window, 0.1 to 50. With
+/// the OSC enabled the OSC shows seeks instead. Returns the applied settings.
+#[tauri::command]
+pub async fn set_osd_bar(
+    player: State<'_, PlayerHandle>,
+    enabled: bool,
+    align_y: Option<f64>,
+    height: Option<f64>,
+) -> Result<OsdBar> {
+    if let Some(align_y) = align_y {
+        if !(-1.0..=1.0).contains(&align_y) {
+            return Err(Error::InvalidArgument(format!(
+                "bar position {align_y} is outside -1..=1"
+            )));
+        }
+    }
+    if let Some(height) = height {
+        if !(0.1..=50.0).contains(&height) {
+            return Err(Error::InvalidArgument(format!(
+                "bar height {height}% is outside 0.1..=50"
+            )));
+        }
+    }
+
+    player.call(move |player| {
+        player.mpv.set_property("osd-bar", enabled)?;
+        if let Some(align_y) = align_y {
+            player.mpv.set_property("osd-bar-align-y", align_y)?;
+        }
+        if let Some(height) = height {
+            player.mpv.set_property("osd-bar-h", height)?;
+        }
+        OsdBar::read(player)
+    })
+}
+
+/// Shows the OSD seek bar and the playback time for a moment, like mpv's
+/// `o` key (`show-progress`). Only the time is shown with `osd-bar` off.
+#[tauri::command]
+pub async fn show_progress(player: State<'_, PlayerHandle>) -> Result<()> {
+    player.call(|player| {
+        player.mpv.command("show-progress", &[])?;
+        player.redraw();
+        Ok(())
+    })
+}
@@ -0,0 +1,893 @@
+use libmpv2::mpv_error;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tauri::State;
+use tokio::sync::oneshot;
+
+use crate::commands::{blocking, playlist};
+use crate::file_settings::FileSettings;
+use crate::player::{wait_for, Player, PlayerHandle};
+use crate::{Error, Result};
+
+/// Step size of `set_speed_ramp`; short enough that the ramp sounds smooth.
+const SPEED_RAMP_STEP: Duration = Duration::from_millis(50);
+
+const HR_SEEK_MODES: &[&str] = &["no", "absolute", "yes", "always", "default"];
+
+/// How often `loop_segment` checks the position. Also roughly how far past
+/// the end the last pass may run before it pauses.
+const LOOP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Largest buffer `load_bytes` takes. mpv keeps the hex text it's handed as
+/// well as the decoded copy, so this costs about three times as much memory.
+const MAX_LOAD_BYTES: usize = 64 * 1024 * 1024;
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+/// `load_bytes` MIME types and the demuxer each one forces.
+const MIME_DEMUXERS: &[(&str, &str)] = &[
+    ("video/mp4", "mp4"),
+    ("audio/mp4", "mp4"),
+    ("video/webm", "matroska"),
+    ("audio/webm", "matroska"),
+    ("video/x-matroska", "matroska"),
+    ("video/ogg", "ogg"),
+    ("audio/ogg", "ogg"),
+    ("audio/mpeg", "mp3"),
+    ("audio/flac", "flac"),
+    ("audio/wav", "wav"),
+];
+
+/// How long `reopen_with` waits for the file to open again.
+const RELOAD_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest `wait_until_loaded` waits, in milliseconds.
+const MAX_LOAD_WAIT_MS: u64 = 5 * 60 * 1000;
+
+/// Everything a basic transport UI shows, for polling instead of events.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaybackStatus {
+    pub paused: bool,
+    /// `None` while nothing is loaded.
+    pub time_pos: Option<f64>,
+    /// `None` while nothing is loaded, or for streams of unknown length.
+    pub duration: Option<f64>,
+    pub volume: f64,
+    pub mute: bool,
+    pub speed: f64,
+    /// Selected track ids, `None` when that kind of track is off.
+    pub vid: Option<i64>,
+    pub aid: Option<i64>,
+    pub sid: Option<i64>,
+    /// `loop-file` and `loop-playlist`: `no`, `inf` or a count.
+    pub loop_file: String,
+    pub loop_playlist: String,
+    /// Whether an A-B loop is set (as by `loop_segment`).
+    pub ab_loop: bool,
+    /// Nothing is loaded and mpv is waiting for a file.
+    pub idle: bool,
+}
+
+impl PlaybackStatus {
+    fn read(player: &Player) -> Result<Self> {
+        let mpv = &player.mpv;
+        Ok(Self {
+            paused: mpv.get_property("pause")?,
+            time_pos: mpv.get_property("time-pos").ok(),
+            duration: mpv.get_property("duration").ok(),
+            volume: mpv.get_property("volume")?,
+            mute: mpv.get_property("mute")?,
+            speed: mpv.get_property("speed")?,
+            vid: mpv.get_property("vid").ok(),
+            aid: mpv.get_property("aid").ok(),
+            sid: mpv.get_property("sid").ok(),
+            loop_file: mpv.get_property("loop-file")?,
+            loop_playlist: mpv.get_property("loop-playlist")?,
+            ab_loop: mpv.get_property::<f64>("ab-loop-a").is_ok(),
+            idle: mpv.get_property("idle-active")?,
+        })
+    }
+}
+
+/// Replaces the current file with `path` (a local path or URL). With
+/// `autoplay` off it loads paused; unset uses `set_autoplay_default`. Paused
+/// files still show their first frame, since mpv decodes it either way and
+/// the render API draws whatever it has. With `set_resume_prompt` on, a
+/// saved position is offered through `mpv-resume-available` rather than
+/// resumed. Emits `mpv-pause` and returns whether the file is paused.
+#[tauri::command]
+pub async fn load_file(
+    player: State<'_, PlayerHandle>,
+    path: String,
+    autoplay: Option<bool>,
+) -> Result<bool> {
+    player.call(move |player| {
+        let paused = !autoplay.unwrap_or(player.autoplay_default);
+        // Set ahead of the load rather than as a per-file option, which mpv
+        // would revert once the file ends.
+        player.mpv.set_property("pause", paused)?;
+        player.mpv.command("loadfile", &[&path, "replace"])?;
+        player.emit("mpv-pause", paused);
+        Ok(paused)
+    })
+}
+
+/// Payload of `mpv-resume-available`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResumeAvailable {
+    pub path: String,
+    /// Where the file was left, in seconds.
+    pub position: f64,
+}
+
+/// Runs in the `on_load` hook with `set_resume_prompt` on. mpv has applied
+/// the file's watch-later state by then, which sets `start` to the saved
+/// position; this takes the position back out, so the file plays from the
+/// beginning, and offers it with `mpv-resume-available` instead.
+pub fn offer_resume(player: &mut Player) {
+    let Ok(start) = player
+        .mpv
+        .get_property::<String>("file-local-options/start")
+    else {
+        return;
+    };
+    // `none` unless watch-later (or a per-file option) set a start.
+    let Ok(position) = start.parse::<f64>() else {
+        return;
+    };
+    if let Err(e) = player.mpv.set_property("file-local-options/start", "none") {
+        println!("Failed to hold back the resume position: {}", e);
+        return;
+    }
+    if position > 0.0 {
+        let path = player.mpv.get_property("path").unwrap_or_default();
+        player.emit("mpv-resume-available", ResumeAvailable { path, position });
+    }
+}
+
+/// Turns mpv's automatic resume into a prompt. With it on, a file that has
+/// watch-later state (saved by `write-watch-later-config` or quitting with
+/// `save-position-on-quit`) starts from the beginning anyway, and
+/// `mpv-resume-available` carries the saved position so the UI can ask
+/// whether to `seek` there. The other saved settings, such as volume or
+/// subtitle delay (see `watch-later-options`), are still applied. Files
+/// without saved state emit nothing. Off by default, which leaves mpv to
+/// resume by itself. Returns the new state.
+#[tauri::command]
+pub async fn set_resume_prompt(player: State<'_, PlayerHandle>, enabled: bool) -> Result<bool> {
+    player.call(move |player| {
+        player.resume_prompt = enabled;
+        Ok(enabled)
+    })
+}
+
+/// Whether `load_file` starts playback when not told either way. On by
+/// default. Returns the new default.
+#[tauri::command]
+pub async fn set_autoplay_default(player: State<'_, PlayerHandle>, enabled: bool) -> Result<bool> {
+    player.call(move |player| {
+        player.autoplay_default = enabled;
+        Ok(enabled)
+    })
+}
+
+/// Plays `data` straight from memory, replacing the current file, so media
+/// fetched or decrypted in JS never has to touch the disk. `mime` forces the
+/// demuxer for formats mpv can't sniff; without it the data is probed.
+///
+/// This goes through mpv's `hex://` protocol, since `loadfile` only takes a
+/// string and `memory://` would stop at the first NUL byte. That makes it
+/// meant for small clips: buffers over 64 MiB are rejected, and the bytes
+/// also arrive as a JSON array, which is slow to send for anything large.
+/// The hex text becomes the playlist entry's filename, so it shows up in
+/// `export_playlist_state`; per-file settings and resume positions skip it.
+#[tauri::command]
+pub async fn load_bytes(
+    player: State<'_, PlayerHandle>,
+    data: Vec<u8>,
+    mime: Option<String>,
+) -> Result<()> {
+    if data.is_empty() || data.len() > MAX_LOAD_BYTES {
+        return Err(Error::InvalidArgument(format!(
+            "{} bytes is outside 1..={MAX_LOAD_BYTES}",
+            data.len()
+        )));
+    }
+    let options = match mime.as_deref() {
+        None => String::new(),
+        Some(mime) => match MIME_DEMUXERS.iter().find(|(known, _)| *known == mime) {
+            Some((_, demuxer)) => format!("demuxer=lavf,demuxer-lavf-format={demuxer}"),
+            None => {
+                return Err(Error::InvalidArgument(format!(
+                    "unsupported MIME type `{mime}`"
+                )))
+            }
+        },
+    };
+
+    // Up to 128 MiB of text, so it's built off the async runtime.
+    let url = blocking(move || {
+        let mut url = String::with_capacity(6 + data.len() * 2);
+        url.push_str("hex://");
+        for byte in &data {
+            url.push(HEX_DIGITS[(byte >> 4) as usize] as char);
+            url.push(HEX_DIGITS[(byte & 0xF) as usize] as char);
+        }
+        Ok(url)
+    })
+    .await?;
+
+    player.call(move |player| playlist::loadfile(player, &url, "replace", &options))
+}
+
+/// Reads the whole `PlaybackStatus` in one round-trip. It's a snapshot, not
+/// live: the properties are read back to back on the render thread, so no
+/// command can change them midway, but mpv keeps playing meanwhile and
+/// `time-pos` may be a frame ahead of the rest.
+#[tauri::command]
+pub async fn get_status(player: State<'_, PlayerHandle>) -> Result<PlaybackStatus> {
+    player.call(|player| PlaybackStatus::read(player))
+}
+
+/// Resolves once the render thread has worked through everything queued
+/// for it before this call, as a barrier between fire-and-forget work and
+/// reading state back. Commands themselves already wait for their own
+/// changes; what can still be pending are updates posted without waiting,
+/// like the window event handlers' redraws and saves. The render thread
+/// handles its queue in order, so the barrier is an empty call that has to
+/// wait its turn.
+#[tauri::command]
+pub async fn flush_commands(player: State<'_, PlayerHandle>) -> Result<()> {
+    player.call(|_| Ok(()))
+}
+
+/// Resolves once the current file has loaded, right away if it already has,
+/// so tracks, duration and so on can be queried without racing the load.
+/// Fails with a timeout error after `timeout_ms`, at most five minutes.
+#[tauri::command]
+pub async fn wait_until_loaded(player: State<'_, PlayerHandle>, timeout_ms: u64) -> Result<()> {
+    if timeout_ms > MAX_LOAD_WAIT_MS {
+        return Err(Error::InvalidArgument(format!(
+            "timeout {timeout_ms}ms is over {MAX_LOAD_WAIT_MS}ms"
+        )));
+    }
+    let (loaded_tx, loaded_rx) = oneshot::channel();
+    player.call(move |player| {
+        if player.file_loaded {
+            loaded_tx.send(()).ok();
+        } else {
+            player.load_waiters.push(loaded_tx);
+        }
+        Ok(())
+    })?;
+
+    wait_for(
+        loaded_rx,
+        Duration::from_millis(timeout_ms),
+        "the file to load",
+    )
+    .await
+}
+
+/// Shows the frame near `time` while the user drags the scrubber. Uses a
+/// fast keyframe seek and holds playback paused until `commit_seek`, so
+/// repeated calls during a drag never let playback run in between.
+///
+/// The render right after the seek keeps the window responsive; the decoded
+/// target frame follows through the regular update callback once mpv has it.
+#[tauri::command]
+pub async fn preview_seek(player: State<'_, PlayerHandle>, time: f64) -> Result<()> {
+    player.call(move |player| {
+        if player.scrub_was_paused.is_none() {
+            player.scrub_was_paused = Some(player.mpv.get_property("pause")?);
+            player.mpv.set_property("pause", true)?;
+        }
+        player
+            .mpv
+            .command("seek", &[&time.to_string(), "absolute+keyframes"])?;
+        player.redraw();
+        Ok(())
+    })
+}
+
+/// Ends a scrub with an exact seek to `time` and restores the pause state
+/// from before the first `preview_seek`. Also works as a plain exact seek.
+#[tauri::command]
+pub async fn commit_seek(player: State<'_, PlayerHandle>, time: f64) -> Result<()> {
+    player.call(move |player| {
+        player
+            .mpv
+            .command("seek", &[&time.to_string(), "absolute+exact"])?;
+        if let Some(was_paused) = player.scrub_was_paused.take() {
+            player.mpv.set_property("pause", was_paused)?;
+        }
+        Ok(())
+    })
+}
+
+/// A span of the stream, in seconds, that can be seeked to without
+/// leaving the demuxer cache.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct SeekableRange {
+    pub start: f64,
+    pub end: f64,
+}
+
+/// Pulls `seekable-ranges` out of a `demuxer-cache-state` node.
+pub fn parse_seekable_ranges(state: &serde_json::Value) -> Vec<SeekableRange> {
+    let Some(ranges) = state["seekable-ranges"].as_array() else {
+        return Vec::new();
+    };
+    ranges
+        .iter()
+        .filter_map(|range| {
+            let start = range["start"].as_f64()?;
+            let end = range["end"].as_f64()?;
+            (start <= end).then_some(SeekableRange { start, end })
+        })
+        .collect()
+}
+
+fn read_seekable_ranges(player: &Player) -> Vec<SeekableRange> {
+    player
+        .get_json("demuxer-cache-state")
+        .map(|state| parse_seekable_ranges(&state))
+        .unwrap_or_default()
+}
+
+/// The cached spans in `state`, sorted and with overlaps merged, for a
+/// buffer bar. Empty unless the file goes through the cache, which local
+/// files normally don't since they're available in full anyway.
+pub fn buffered_ranges(player: &Player, state: &serde_json::Value) -> Vec<SeekableRange> {
+    let cached = player
+        .mpv
+        .get_property("demuxer-via-network")
+        .unwrap_or(false)
+        || player.mpv.get_property::<String>("cache").ok().as_deref() == Some("yes");
+    if !cached {
+        return Vec::new();
+    }
+
+    let mut ranges = parse_seekable_ranges(state);
+    ranges.sort_by(|a, b| a.start.total_cmp(&b.start));
+    let mut merged: Vec<SeekableRange> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+/// Moves `time` to the closest point inside any of `ranges`. Left alone if
+/// it's already inside one, or if there are none.
+fn clamp_to_ranges(time: f64, ranges: &[SeekableRange]) -> f64 {
+    ranges
+        .iter()
+        .map(|range| time.clamp(range.start, range.end))
+        .min_by(|a, b| (a - time).abs().total_cmp(&(b - time).abs()))
+        .unwrap_or(time)
+}
+
+/// Lists the cached spans of the current file. On a live stream this is the
+/// DVR window; `mpv-seekable-ranges` is emitted whenever it shifts.
+#[tauri::command]
+pub async fn get_seekable_ranges(player: State<'_, PlayerHandle>) -> Result<Vec<SeekableRange>> {
+    player.call(|player| Ok(read_seekable_ranges(player)))
+}
+
+/// Lists the buffered spans of the current file in order, for drawing a
+/// buffer bar over the scrubber; `mpv-buffered-ranges` is emitted whenever
+/// they change. Local files that aren't cached give an empty list, since
+/// all of them is available.
+#[tauri::command]
+pub async fn get_buffered_ranges(player: State<'_, PlayerHandle>) -> Result<Vec<SeekableRange>> {
+    player.call(|player| {
+        let state = player.get_json("demuxer-cache-state").unwrap_or_default();
+        Ok(buffered_ranges(player, &state))
+    })
+}
+
+/// Seeks to `time`. On streams that can only seek within the cache, such as
+/// live streams, a target outside the seekable ranges is moved to the
+/// nearest one rather than failing. Returns the time actually seeked to.
+#[tauri::command]
+pub async fn seek(player: State<'_, PlayerHandle>, time: f64) -> Result<f64> {
+    player.call(move |player| {
+        let cache_only = player
+            .mpv
+            .get_property("partially-seekable")
+            .unwrap_or(false);
+        let target = if cache_only {
+            clamp_to_ranges(time, &read_seekable_ranges(player))
+        } else {
+            time
+        };
+        player
+            .mpv
+            .command("seek", &[&target.to_string(), "absolute"])?;
+        Ok(target)
+    })
+}
+
+/// Plays the current file again from the start, emitting `mpv-time-pos`
+/// with 0. Works from the end of a file kept open by `keep-open` too: the
+/// seek goes first, since unpausing at the end would just end the file.
+/// Fails if nothing is loaded.
+#[tauri::command]
+pub async fn restart(player: State<'_, PlayerHandle>) -> Result<()> {
+    player.call(|player| {
+        if !player.file_loaded || player.mpv.get_property("idle-active")? {
+            return Err(Error::InvalidArgument("nothing is loaded".into()));
+        }
+        player.mpv.command("seek", &["0", "absolute+exact"])?;
+        player.mpv.set_property("pause", false)?;
+        player.emit("mpv-time-pos", 0.0);
+        Ok(())
+    })
+}
+
+/// Seeks `direction` steps from the current position, forward when positive
+/// and back when negative, for arrow keys and jog wheels. The step is 5 s
+/// unless changed with `set_seek_step`. Stays within the file, emits
+/// `mpv-time-pos` with the target and returns it.
+#[tauri::command]
+pub async fn seek_step(player: State<'_, PlayerHandle>, direction: i32) -> Result<f64> {
+    player.call(move |player| {
+        let time: f64 = player.mpv.get_property("time-pos")?;
+        let mut target = (time + direction as f64 * player.seek_step).max(0.0);
+        if let Ok(duration) = player.mpv.get_property::<f64>("duration") {
+            target = target.min(duration);
+        }
+        if player
+            .mpv
+            .get_property("partially-seekable")
+            .unwrap_or(false)
+        {
+            target = clamp_to_ranges(target, &read_seekable_ranges(player));
+        }
+        player
+            .mpv
+            .command("seek", &[&target.to_string(), "absolute"])?;
+        player.emit("mpv-time-pos", target);
+        Ok(target)
+    })
+}
+
+/// Sets how far each `seek_step` moves, in seconds. Returns the new step.
+#[tauri::command]
+pub async fn set_seek_step(player: State<'_, PlayerHandle>, secs: f64) -> Result<f64> {
+    if !(secs > 0.0 && secs.is_finite()) {
+        return Err(Error::InvalidArgument(format!(
+            "seek step {secs}s isn't positive"
+        )));
+    }
+
+    player.call(move |player| {
+        player.seek_step = secs;
+        Ok(secs)
+    })
+}
+
+/// The frame rate the container declares (`container-fps`), or an error
+/// if it doesn't declare one, as with audio-only files.
+fn container_fps(player: &Player) -> Result<f64> {
+    player
+        .mpv
+        .get_property::<f64>("container-fps")
+        .ok()
+        .filter(|fps| *fps > 0.0 && fps.is_finite())
+        .ok_or_else(|| Error::InvalidArgument("the frame rate is unknown".into()))
+}
+
+/// The index of the frame at the current position, counted from 0, as
+/// `time-pos` times the container's frame rate. That assumes a constant
+/// frame rate: on variable frame rate files the number is only an
+/// approximation, off by however far the actual timing drifts from the
+/// declared rate. Fails if the rate is unknown.
+#[tauri::command]
+pub async fn get_frame_number(player: State<'_, PlayerHandle>) -> Result<i64> {
+    player.call(|player| {
+        let fps = container_fps(player)?;
+        let time: f64 = player.mpv.get_property("time-pos")?;
+        Ok((time * fps).round() as i64)
+    })
+}
+
+/// Exact-seeks to frame `n`, the inverse of `get_frame_number` and with
+/// the same constant frame rate approximation. Returns the time seeked to.
+#[tauri::command]
+pub async fn seek_to_frame(player: State<'_, PlayerHandle>, n: i64) -> Result<f64> {
+    if n < 0 {
+        return Err(Error::InvalidArgument(format!("frame {n} is negative")));
+    }
+
+    player.call(move |player| {
+        let time = n as f64 / container_fps(player)?;
+        player
+            .mpv
+            .command("seek", &[&time.to_string(), "absolute+exact"])?;
+        Ok(time)
+    })
+}
+
+/// Runs `apply` to change options that only take effect when a file is
+/// opened, then reopens the current file and continues from the same
+/// position. Returns how long reopening took, or `None` if nothing was
+/// loaded, in which case `apply` has still run.
+pub async fn reopen_with(
+    player: &PlayerHandle,
+    apply: impl FnOnce(&mut Player) -> Result<()> + Send + 'static,
+) -> Result<Option<Duration>> {
+    let (loaded_tx, loaded_rx) = oneshot::channel();
+    let reopened = player.call(move |player| {
+        apply(player)?;
+        if !player.file_loaded {
+            return Ok(None);
+        }
+        let position: Option<f64> = player.mpv.get_property("time-pos").ok();
+        player.load_waiters.push(loaded_tx);
+        player.mpv.command("playlist-play-index", &["current"])?;
+        Ok(Some((position, Instant::now())))
+    })?;
+    let Some((position, started)) = reopened else {
+        return Ok(None);
+    };
+
+    wait_for(loaded_rx, RELOAD_TIMEOUT, "the file to reopen").await?;
+    let took = started.elapsed();
+    if let Some(position) = position {
+        player.call(move |player| {
+            player
+                .mpv
+                .command("seek", &[&position.to_string(), "absolute+exact"])?;
+            Ok(())
+        })?;
+    }
+    Ok(Some(took))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProbeOpts {
+    /// Bytes lavf reads to detect the streams (`demuxer-lavf-probesize`),
+    /// 32 to 2^31 - 1.
+    pub probesize: i64,
+    /// Seconds of data lavf analyzes for stream parameters
+    /// (`demuxer-lavf-analyzeduration`), 0 to 3600.
+    pub analyzeduration: f64,
+}
+
+/// Makes lavf probe more of the input before deciding what streams it has,
+/// for MPEG-TS and other streams where video or audio goes undetected with
+/// the defaults. The current file is reopened so this applies right away,
+/// and playback continues from the same position once it has. Returns the
+/// applied values.
+#[tauri::command]
+pub async fn set_probe_options(
+    player: State<'_, PlayerHandle>,
+    opts: ProbeOpts,
+) -> Result<ProbeOpts> {
+    if !(32..=i32::MAX as i64).contains(&opts.probesize) {
+        return Err(Error::InvalidArgument(format!(
+            "probe size {} is outside 32..=2147483647",
+            opts.probesize
+        )));
+    }
+    if !(0.0..=3600.0).contains(&opts.analyzeduration) {
+        return Err(Error::InvalidArgument(format!(
+            "analyze duration {}s is outside 0..=3600",
+            opts.analyzeduration
+        )));
+    }
+
+    reopen_with(&player, move |player| {
+        player
+            .mpv
+            .set_property("demuxer-lavf-probesize", opts.probesize)?;
+        player
+            .mpv
+            .set_property("demuxer-lavf-analyzeduration", opts.analyzeduration)?;
+        Ok(())
+    })
+    .await?;
+
+    player.call(|player| {
+        Ok(ProbeOpts {
+            probesize: player.mpv.get_property("demuxer-lavf-probesize")?,
+            analyzeduration: player.mpv.get_property("demuxer-lavf-analyzeduration")?,
+        })
+    })
+}
+
+/// Sets how far ahead the demuxer buffers (`demuxer-readahead-secs`), so
+/// forward scrubs land in already-read data more often. Returns the applied
+/// value.
+#[tauri::command]
+pub async fn set_demuxer_readahead(player: State<'_, PlayerHandle>, secs: f64) -> Result<f64> {
+    if !(secs >= 0.0 && secs.is_finite()) {
+        return Err(Error::InvalidArgument(format!(
+            "readahead {secs}s is negative or not finite"
+        )));
+    }
+
+    player.call(move |player| {
+        player.mpv.set_property("demuxer-readahead-secs", secs)?;
+        Ok(player.mpv.get_property("demuxer-readahead-secs")?)
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HrSeek {
+    pub mode: String,
+    pub framedrop: bool,
+}
+
+/// Picks which seeks decode up to the exact target (`hr-seek`: `no`,
+/// `absolute`, `yes`, `always` or `default`) and whether frames may be
+/// dropped to get there faster (`hr-seek-framedrop`). `seek` follows this;
+/// `preview_seek` always goes to keyframes and `commit_seek` is always
+/// exact. Returns the applied values.
+#[tauri::command]
+pub async fn set_hr_seek(
+    player: State<'_, PlayerHandle>,
+    mode: String,
+    framedrop: Option<bool>,
+) -> Result<HrSeek> {
+    if !HR_SEEK_MODES.contains(&mode.as_str()) {
+        return Err(Error::InvalidArgument(format!(
+            "unknown hr-seek mode `{mode}`"
+        )));
+    }
+
+    player.call(move |player| {
+        player.mpv.set_property("hr-seek", mode)?;
+        if let Some(framedrop) = framedrop {
+            player.mpv.set_property("hr-seek-framedrop", framedrop)?;
+        }
+        Ok(HrSeek {
+            mode: player.mpv.get_property("hr-seek")?,
+            framedrop: player.mpv.get_property("hr-seek-framedrop")?,
+        })
+    })
+}
+
+/// Stores the current file's volume, subtitle delay and selected tracks now,
+/// rather than waiting for it to be unloaded. Returns what was saved, or
+/// `None` if nothing is loaded.
+#[tauri::command]
+pub async fn save_file_settings(player: State<'_, PlayerHandle>) -> Result<Option<FileSettings>> {
+    player.call(|player| player.save_file_settings())
+}
+
+/// Looks up the remembered settings for `path`. They're applied
+/// automatically whenever that file loads, so this is for display only.
+#[tauri::command]
+pub async fn load_file_settings(
+    player: State<'_, PlayerHandle>,
+    path: String,
+) -> Result<Option<FileSettings>> {
+    player.call(move |player| {
+        Ok(player
+            .file_settings
+            .as_ref()
+            .and_then(|store| store.get(&path))
+            .cloned())
+    })
+}
+
+fn check_speed(speed: f64) -> Result<()> {
+    // mpv's own limits for `speed`.
+    if !(0.01..=100.0).contains(&speed) {
+        return Err(Error::InvalidArgument(format!(
+            "speed {speed} is outside 0.01..=100"
+        )));
+    }
+    Ok(())
+}
+
+fn apply_speed(player: &Player, speed: f64) -> Result<()> {
+    player.mpv.set_property("speed", speed)?;
+    player.emit("mpv-speed", speed);
+    Ok(())
+}
+
+/// Sets the playback speed immediately, cancelling any ramp in progress.
+#[tauri::command]
+pub async fn set_speed(player: State<'_, PlayerHandle>, speed: f64) -> Result<()> {
+    check_speed(speed)?;
+    player.call(move |player| {
+        player.cancel_timer("speed-ramp");
+        apply_speed(player, speed)
+    })
+}
+
+/// Moves the playback speed linearly to `target` over `duration_ms`,
+/// emitting `mpv-speed` at each step. Another `set_speed` or
+/// `set_speed_ramp` call takes over from wherever the ramp has got to.
+#[tauri::command]
+pub async fn set_speed_ramp(
+    player: State<'_, PlayerHandle>,
+    target: f64,
+    duration_ms: u64,
+) -> Result<()> {
+    check_speed(target)?;
+    player.call(move |player| {
+        player.cancel_timer("speed-ramp");
+        if duration_ms == 0 {
+            return apply_speed(player, target);
+        }
+
+        let start: f64 = player.mpv.get_property("speed")?;
+        let duration = Duration::from_millis(duration_ms);
+        let started = Instant::now();
+        player.set_timer("speed-ramp", SPEED_RAMP_STEP, move |player| {
+            let t = (started.elapsed().as_secs_f64() / duration.as_secs_f64()).min(1.0);
+            if let Err(e) = apply_speed(player, start + (target - start) * t) {
+                println!("Speed ramp stopped: {}", e);
+                return false;
+            }
+            t < 1.0
+        });
+        Ok(())
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoopProgress {
+    /// Passes through the segment finished so far.
+    pub completed: u32,
+    pub count: u32,
+}
+
+fn clear_ab_loop(player: &Player) -> Result<()> {
+    player.mpv.set_property("ab-loop-a", "no")?;
+    player.mpv.set_property("ab-loop-b", "no")?;
+    set_ab_loop_count(player, "inf")?;
+    Ok(())
+}
+
+/// Sets `ab-loop-count`, which mpv only has since 0.36. Returns `false` if
+/// this mpv doesn't know it, in which case the A-B loop has no limit and
+/// keeps jumping back until it's cleared.
+fn set_ab_loop_count(player: &Player, count: &str) -> Result<bool> {
+    match player.mpv.set_property("ab-loop-count", count) {
+        Ok(()) => Ok(true),
+        Err(libmpv2::Error::Raw(mpv_error::PropertyNotFound)) => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Plays `start..end` (seconds) `count` times, then clears the loop and
+/// pauses at `end`. Emits `mpv-loop-progress` after every pass. A `count`
+/// of 0 cancels a running loop.
+///
+/// mpv's A-B loop does the jumping, limited by `ab-loop-count` so the last
+/// pass runs past `end`; passes are counted by watching `time-pos` wrap.
+/// mpv before 0.36 has no `ab-loop-count`, so there the loop is cleared
+/// instead as the last pass starts.
+#[tauri::command]
+pub async fn loop_segment(
+    player: State<'_, PlayerHandle>,
+    start: f64,
+    end: f64,
+    count: u32,
+) -> Result<()> {
+    if count > 0 && !(start >= 0.0 && start < end) {
+        return Err(Error::InvalidArgument(format!(
+            "loop segment {start}..{end} is empty or negative"
+        )));
+    }
+
+    player.call(move |player| {
+        player.cancel_timer("loop-segment");
+        clear_ab_loop(player)?;
+        if count == 0 {
+            return Ok(());
+        }
+
+        player.mpv.set_property("ab-loop-a", start)?;
+        player.mpv.set_property("ab-loop-b", end)?;
+        let limited = set_ab_loop_count(player, &(count - 1).to_string())?;
+        if !limited && count == 1 {
+            clear_ab_loop(player)?;
+        }
+        player
+            .mpv
+            .command("seek", &[&start.to_string(), "absolute+exact"])?;
+
+        // Only a jump back from the last stretch of the segment is a wrap,
+        // not any backwards seek the user makes.
+        let wrap_zone = end - ((end - start) / 2.0).min(1.0);
+        let mut last_pos = start;
+        let mut completed = 0;
+        player.set_timer("loop-segment", LOOP_POLL_INTERVAL, move |player| {
+            let Ok(pos) = player.mpv.get_property::<f64>("time-pos") else {
+                return true;
+            };
+            let wrapped = pos < last_pos && last_pos >= wrap_zone;
+            let finished = completed + 1 == count && pos >= end;
+            last_pos = pos;
+            if !wrapped && !finished {
+                return true;
+            }
+
+            completed += 1;
+            player.emit("mpv-loop-progress", LoopProgress { completed, count });
+            if completed < count {
+                if !limited && completed + 1 == count {
+                    if let Err(e) = clear_ab_loop(player) {
+                        println!("Failed to release the last loop pass: {}", e);
+                    }
+                }
+                return true;
+            }
+            if let Err(e) = clear_ab_loop(player) {
+                println!("Failed to clear segment loop: {}", e);
+            }
+            player.mpv.set_property("pause", true).ok();
+            false
+        });
+        Ok(())
+    })
+}
+
+/// Properties `reset_player` puts back to mpv's defaults. Speed, volume
+/// fades and the A-B loop are reset separately, since they have timers of
+/// their own.
+const PLAYER_DEFAULTS: &[(&str, &str)] = &[
+    ("volume", "100"),
+    ("mute", "no"),
+    ("audio-delay", "0"),
+    ("sub-delay", "0"),
+    ("video-zoom", "0"),
+    ("video-pan-x", "0"),
+    ("video-pan-y", "0"),
+    ("video-rotate", "0"),
+    ("brightness", "0"),
+    ("contrast", "0"),
+    ("gamma", "0"),
+    ("saturation", "0"),
+    ("hue", "0"),
+    ("loop-file", "no"),
+    ("loop-playlist", "no"),
+];
+
+/// Puts volume, speed, delays, zoom/pan, the video equalizer, loops and all
+/// video and audio filters back to their defaults, keeping the current file
+/// loaded. Emits `mpv-status` with the result and returns it too.
+///
+/// Filters are cleared wholesale, so labelled ones like `set_3d_mode`,
+/// `set_max_resolution` or `set_visualizer` are dropped as well.
+#[tauri::command]
+pub async fn reset_player(player: State<'_, PlayerHandle>) -> Result<PlaybackStatus> {
+    player.call(|player| {
+        player.cancel_timer("speed-ramp");
+        player.cancel_timer("loop-segment");
+        player.cancel_timer("audio-fade");
+        player.cancel_timer("visualizer");
+        player.audio_fade_volume = None;
+        // A loop that can't be cleared shouldn't stop everything else from
+        // being reset.
+        if let Err(e) = clear_ab_loop(player) {
+            println!("Failed to clear the A-B loop: {}", e);
+        }
+        apply_speed(player, 1.0)?;
+        for (name, value) in PLAYER_DEFAULTS {
+            player.mpv.set_property(name, *value)?;
+        }
+        player.av_delay_compensation_ms = 0;
+        player.mpv.command("vf", &["clr", ""])?;
+        player.mpv.command("af", &["clr", ""])?;
+        player.redraw();
+
+        let status = PlaybackStatus::read(player)?;
+        player.emit("mpv-status", status.clone());
+        Ok(status)
+    })
+}
@@ -0,0 +1,334 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+use tauri::State;
+use tokio::sync::oneshot;
+
+use crate::player::{wait_for, Player, PlayerHandle};
+use crate::{Error, Result};
+
+/// How long `cancel_peek` waits for the original entry to load again.
+const PEEK_RESTORE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaylistEntry {
+    /// Path or URL, as it was loaded.
+    pub filename: String,
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaylistItemState {
+    #[serde(flatten)]
+    pub entry: PlaylistEntry,
+    /// Where to resume, in seconds; `None` starts from the beginning.
+    pub position: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaylistState {
+    pub items: Vec<PlaylistItemState>,
+    /// Index into `items` of the entry that was playing.
+    pub current: Option<usize>,
+}
+
+/// Where playback was when a `peek_next`/`peek_prev` preview started, so
+/// `cancel_peek` can go back there.
+pub struct Peek {
+    origin: usize,
+    position: Option<f64>,
+    was_paused: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PeekPreview {
+    pub index: usize,
+    #[serde(flatten)]
+    pub entry: PlaylistEntry,
+}
+
+/// Reads mpv's `playlist` into entries, in playlist order.
+pub fn read_playlist(player: &Player) -> Result<Vec<PlaylistEntry>> {
+    let playlist = player.get_json("playlist")?;
+    Ok(playlist
+        .as_array()
+        .map(|entries| {
+            entries
+                .iter()
+                .filter_map(|entry| {
+                    Some(PlaylistEntry {
+                        filename: entry["filename"].as_str()?.to_owned(),
+                        title: entry["title"].as_str().map(str::to_owned),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// Index of the playing entry, `None` while idle.
+pub fn playlist_pos(player: &Player) -> Option<usize> {
+    let pos: i64 = player.mpv.get_property("playlist-pos").ok()?;
+    usize::try_from(pos).ok()
+}
+
+/// Builds the `loadfile` per-file options that keep an entry's title and
+/// resume position.
+fn loadfile_options(item: &PlaylistItemState) -> String {
+    let mut options = Vec::new();
+    if let Some(title) = &item.entry.title {
+        // `%len%` quoting lets the title contain commas and `=`.
+        options.push(format!("force-media-title=%{}%{}", title.len(), title));
+    }
+    if let Some(position) = item.position {
+        options.push(format!("start={position}"));
+    }
+    options.join(",")
+}
+
+/// Runs `loadfile` with per-file `options`. mpv 0.38 put an insertion index
+/// before the options, which older versions would read as the options
+/// themselves, so it's only passed to versions that expect it.
+pub fn loadfile(player: &mut Player, url: &str, flags: &str, options: &str) -> Result<()> {
+    let version: String = player.mpv.get_property("mpv-version").unwrap_or_default();
+    if loadfile_takes_index(&version) {
+        player
+            .mpv
+            .command("loadfile", &[url, flags, "-1", options])?;
+    } else {
+        player.mpv.command("loadfile", &[url, flags, options])?;
+    }
+    Ok(())
+}
+
+/// Whether an `mpv-version` such as `mpv 0.37.0` or `mpv v0.38.0-dirty` is
+/// 0.38 or later. Versions that don't parse, like bare git hashes, are
+/// taken to be recent.
+fn loadfile_takes_index(version: &str) -> bool {
+    let mut numbers = version
+        .trim_start_matches("mpv ")
+        .trim_start_matches('v')
+        .split(['.', '-'])
+        .map(|part| part.parse::<u32>().ok());
+    match (numbers.next().flatten(), numbers.next().flatten()) {
+        (Some(major), Some(minor)) => (major, minor) >= (0, 38),
+        _ => true,
+    }
+}
+
+/// Captures the playlist for session restore: every entry, the one playing
+/// and where each was left off. Positions come from the last time an entry
+/// was unloaded, or the live position for the one playing; entries never
+/// played have none.
+#[tauri::command]
+pub async fn export_playlist_state(player: State<'_, PlayerHandle>) -> Result<PlaylistState> {
+    player.call(|player| {
+        let current = playlist_pos(player);
+        let items = read_playlist(player)?
+            .into_iter()
+            .enumerate()
+            .map(|(index, entry)| {
+                let position = if Some(index) == current {
+                    player.mpv.get_property("time-pos").ok()
+                } else {
+                    player.playlist_positions.get(&entry.filename).copied()
+                };
+                PlaylistItemState { entry, position }
+            })
+            .collect();
+        Ok(PlaylistState { items, current })
+    })
+}
+
+/// Replaces the playlist with `state` and resumes its current entry (or the
+/// first) at the saved position. Local files that no longer exist are left
+/// out with a warning; their names are returned. If nothing is left, the
+/// playlist isn't touched.
+#[tauri::command]
+pub async fn import_playlist_state(
+    player: State<'_, PlayerHandle>,
+    state: PlaylistState,
+) -> Result<Vec<String>> {
+    let mut skipped = Vec::new();
+    let mut current = None;
+    let mut items = Vec::new();
+    for (index, item) in state.items.into_iter().enumerate() {
+        let filename = &item.entry.filename;
+        if !filename.contains("://") && !Path::new(filename).exists() {
+            println!("Skipping missing playlist entry {}", filename);
+            skipped.push(item.entry.filename);
+            continue;
+        }
+        if Some(index) == state.current {
+            current = Some(items.len());
+        }
+        items.push(item);
+    }
+    if items.is_empty() {
+        return Ok(skipped);
+    }
+
+    player.call(move |player| {
+        // `playlist-clear` keeps the playing entry, if any, at index 0. The
+        // new entries go after it and it's removed once it's been left.
+        player.mpv.command("playlist-clear", &[])?;
+        let offset: i64 = player.mpv.get_property("playlist-count")?;
+
+        for item in &items {
+            loadfile(
+                player,
+                &item.entry.filename,
+                "append",
+                &loadfile_options(item),
+            )?;
+        }
+
+        let start = offset + current.unwrap_or(0) as i64;
+        player
+            .mpv
+            .command("playlist-play-index", &[&start.to_string()])?;
+        if offset > 0 {
+            player.mpv.command("playlist-remove", &["0"])?;
+        }
+        Ok(skipped)
+    })
+}
+
+/// Index of the playing entry, `None` while idle. Changes are also sent as
+/// `mpv-playlist-pos`, with -1 for none.
+#[tauri::command]
+pub async fn get_playlist_pos(player: State<'_, PlayerHandle>) -> Result<Option<usize>> {
+    player.call(|player| Ok(playlist_pos(player)))
+}
+
+/// Jumps to the entry at `index` and returns it. Fails if there's no such
+/// entry.
+#[tauri::command]
+pub async fn set_playlist_pos(player: State<'_, PlayerHandle>, index: usize) -> Result<usize> {
+    player.call(move |player| {
+        let count: i64 = player.mpv.get_property("playlist-count")?;
+        if index as i64 >= count {
+            return Err(Error::InvalidArgument(format!(
+                "playlist index {index} is out of range for {count} entries"
+            )));
+        }
+        player.mpv.set_property("playlist-pos", index as i64)?;
+        Ok(index)
+    })
+}
+
+/// Loads the entry `step` away from the one showing, paused at its first
+/// frame, remembering where playback was if this starts a preview.
+fn peek(player: &mut Player, step: i64) -> Result<PeekPreview> {
+    let current =
+        playlist_pos(player).ok_or_else(|| Error::InvalidArgument("nothing is playing".into()))?;
+    let entries = read_playlist(player)?;
+    let index = usize::try_from(current as i64 + step)
+        .ok()
+        .filter(|index| *index < entries.len())
+        .ok_or_else(|| {
+            Error::InvalidArgument(format!("no playlist entry {step:+} from {current}"))
+        })?;
+
+    if player.peek.is_none() {
+        player.peek = Some(Peek {
+            origin: current,
+            position: player.mpv.get_property("time-pos").ok(),
+            was_paused: player.mpv.get_property("pause")?,
+        });
+    }
+    player.mpv.set_property("pause", true)?;
+    player.mpv.set_property("playlist-pos", index as i64)?;
+
+    let preview = PeekPreview {
+        index,
+        entry: entries[index].clone(),
+    };
+    player.emit("mpv-peek", preview.clone());
+    Ok(preview)
+}
+
+/// Previews the next playlist entry, paused at its first frame, and emits
+/// `mpv-peek`. Repeated calls keep stepping; `confirm_peek` plays the entry
+/// shown and `cancel_peek` goes back to where the first peek started.
+#[tauri::command]
+pub async fn peek_next(player: State<'_, PlayerHandle>) -> Result<PeekPreview> {
+    player.call(|player| peek(player, 1))
+}
+
+/// Same as `peek_next`, for the previous entry.
+#[tauri::command]
+pub async fn peek_prev(player: State<'_, PlayerHandle>) -> Result<PeekPreview> {
+    player.call(|player| peek(player, -1))
+}
+
+/// Plays the previewed entry from the start and ends the preview, emitting
+/// `mpv-peek-end` with `true`. Returns `false` if there was no preview.
+#[tauri::command]
+pub async fn confirm_peek(player: State<'_, PlayerHandle>) -> Result<bool> {
+    player.call(|player| {
+        if player.peek.take().is_none() {
+            return Ok(false);
+        }
+        player.mpv.set_property("pause", false)?;
+        player.emit("mpv-peek-end", true);
+        Ok(true)
+    })
+}
+
+/// Ends the preview by reloading the entry that was playing before it, at
+/// the same position and pause state, emitting `mpv-peek-end` with `false`.
+/// Resolves once it's back. Returns `false` if there was no preview.
+#[tauri::command]
+pub async fn cancel_peek(player: State<'_, PlayerHandle>) -> Result<bool> {
+    let (loaded_tx, loaded_rx) = oneshot::channel();
+    let peek = player.call(move |player| {
+        let Some(peek) = player.peek.take() else {
+            return Ok(None);
+        };
+        if playlist_pos(player) == Some(peek.origin) {
+            loaded_tx.send(()).ok();
+        } else {
+            player.load_waiters.push(loaded_tx);
+            player
+                .mpv
+                .set_property("playlist-pos", peek.origin as i64)?;
+        }
+        Ok(Some(peek))
+    })?;
+    let Some(peek) = peek else {
+        return Ok(false);
+    };
+
+    wait_for(
+        loaded_rx,
+        PEEK_RESTORE_TIMEOUT,
+        "the previous entry to reload",
+    )
+    .await?;
+
+    player.call(move |player| {
+        if let Some(position) = peek.position {
+            player
+                .mpv
+                .command("seek", &[&position.to_string(), "absolute+exact"])?;
+        }
+        player.mpv.set_property("pause", peek.was_paused)?;
+        player.emit("mpv-peek-end", false);
+        Ok(true)
+    })
+}
+
+/// Opens the next playlist entry while the current one is still playing
+/// (`prefetch-playlist`), so advancing skips the wait for the network and
+/// the demuxer. Pair it with `gapless-audio` for audio that runs straight
+/// on. mpv only prefetches once the current file's cache has been read to
+/// the end, and the prefetched file is dropped if the playlist changes
+/// meanwhile. Returns whether prefetching is on.
+#[tauri::command]
+pub async fn set_prefetch_next(player: State<'_, PlayerHandle>, enabled: bool) -> Result<bool> {
+    player.call(move |player| {
+        player.mpv.set_property("prefetch-playlist", enabled)?;
+        Ok(player.mpv.get_property("prefetch-playlist")?)
+    })
+}
@@ -0,0 +1,348 @@
+use serde::Serialize;
+use tauri::State;
+
+use crate::commands::{playback, tracks};
+use crate::mkv::{self, Attachment};
+use crate::player::{Player, PlayerHandle};
+use crate::{Error, Result};
+
+const ASS_OVERRIDE_MODES: &[&str] = &["no", "yes", "force", "scale", "strip"];
+
+/// Furthest `nudge_sub_delay` moves subtitles either way, in seconds.
+const MAX_SUB_DELAY: f64 = 600.0;
+
+/// Controls how much of an ASS script's own styling mpv overrides with the
+/// `sub-*` options (`no`/`yes`/`scale`/`force`/`strip`). Returns the applied mode.
+#[tauri::command]
+pub async fn set_ass_override(player: State<'_, PlayerHandle>, mode: String) -> Result<String> {
+    if !ASS_OVERRIDE_MODES.contains(&mode.as_str()) {
+        return Err(Error::InvalidArgument(format!(
+            "unknown ASS override mode `{mode}`"
+        )));
+    }
+
+    player.call(move |player| {
+        player.mpv.set_property("sub-ass-override", mode)?;
+        player.redraw();
+        Ok(player.mpv.get_property("sub-ass-override")?)
+    })
+}
+
+/// Sets the font used for plain-text subs, and for ASS subs when
+/// `sub-ass-override` is `force`. Returns the applied font name.
+#[tauri::command]
+pub async fn set_sub_font(player: State<'_, PlayerHandle>, name: String) -> Result<String> {
+    player.call(move |player| {
+        player.mpv.set_property("sub-font", name)?;
+        player.redraw();
+        Ok(player.mpv.get_property("sub-font")?)
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubScale {
+    /// Whether subtitle size follows the window rather than the video.
+    pub scale_with_window: bool,
+    /// Extra factor on top of that, 1.0 being the default size.
+    pub scale: f64,
+}
+
+impl SubScale {
+    fn read(player: &Player) -> Result<Self> {
+        Ok(Self {
+            scale_with_window: player.mpv.get_property("sub-scale-with-window")?,
+            scale: player.mpv.get_property("sub-scale")?,
+        })
+    }
+}
+
+/// Makes subtitles grow and shrink with the window (`sub-scale-by-window`
+/// and `sub-scale-with-window`), so they stay readable in a small window.
+/// Returns the current scaling.
+#[tauri::command]
+pub async fn set_sub_scale_with_window(
+    player: State<'_, PlayerHandle>,
+    enabled: bool,
+) -> Result<SubScale> {
+    player.call(move |player| {
+        player.mpv.set_property("sub-scale-by-window", enabled)?;
+        player.mpv.set_property("sub-scale-with-window", enabled)?;
+        player.redraw();
+        SubScale::read(player)
+    })
+}
+
+/// Scales subtitles by `factor` (`sub-scale`, 0.01 to 100). Returns the
+/// current scaling.
+#[tauri::command]
+pub async fn set_sub_scale(player: State<'_, PlayerHandle>, factor: f64) -> Result<SubScale> {
+    if !(0.01..=100.0).contains(&factor) {
+        return Err(Error::InvalidArgument(format!(
+            "subtitle scale {factor} is outside 0.01..=100"
+        )));
+    }
+
+    player.call(move |player| {
+        player.mpv.set_property("sub-scale", factor)?;
+        player.redraw();
+        SubScale::read(player)
+    })
+}
+
+// Newer mpv calls `sub-ass-force-margins` `sub-ass-use-margins`; older
+// builds only know the old name.
+const ASS_MARGINS: &str = "sub-ass-use-margins";
+const ASS_MARGINS_OLD: &str = "sub-ass-force-margins";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubMargins {
+    /// Whether plain-text subs may be placed in the black bars.
+    pub use_margins: bool,
+    /// Whether ASS subs may be, for scripts that don't position themselves.
+    pub ass_force_margins: bool,
+}
+
+impl SubMargins {
+    fn read(player: &Player) -> Result<Self> {
+        Ok(Self {
+            use_margins: player.mpv.get_property("sub-use-margins")?,
+            ass_force_margins: match player.mpv.get_property(ASS_MARGINS) {
+                Ok(enabled) => enabled,
+                Err(_) => player.mpv.get_property(ASS_MARGINS_OLD)?,
+            },
+        })
+    }
+}
+
+/// Lets plain-text subtitles move into the letterbox bars
+/// (`sub-use-margins`). Turn it off on ultrawide screens to keep them
+/// inside the picture. Returns the current margin settings.
+#[tauri::command]
+pub async fn set_sub_use_margins(
+    player: State<'_, PlayerHandle>,
+    enabled: bool,
+) -> Result<SubMargins> {
+    player.call(move |player| {
+        player.mpv.set_property("sub-use-margins", enabled)?;
+        player.redraw();
+        SubMargins::read(player)
+    })
+}
+
+/// Same as `set_sub_use_margins`, for ASS subtitles
+/// (`sub-ass-force-margins`). Returns the current margin settings.
+#[tauri::command]
+pub async fn set_sub_ass_force_margins(
+    player: State<'_, PlayerHandle>,
+    enabled: bool,
+) -> Result<SubMargins> {
+    player.call(move |player| {
+        if player.mpv.set_property(ASS_MARGINS, enabled).is_err() {
+            player.mpv.set_property(ASS_MARGINS_OLD, enabled)?;
+        }
+        player.redraw();
+        SubMargins::read(player)
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubBackend {
+    /// Whether subtitles are drawn at all (`sub-visibility`).
+    pub visible: bool,
+    /// Whether ASS subs get their own styling from libass (`sub-ass`), as
+    /// opposed to being drawn as plain text.
+    pub native_ass: bool,
+}
+
+/// Picks how much subtitle rendering to do: `libass` for full ASS styling,
+/// `plain` to draw ASS subs as plain text, which is much cheaper for heavily
+/// typeset scripts on weak devices, or `none` to skip subtitles entirely.
+/// Returns the applied state.
+#[tauri::command]
+pub async fn set_sub_backend(player: State<'_, PlayerHandle>, mode: String) -> Result<SubBackend> {
+    let (visible, native_ass) = match mode.as_str() {
+        "libass" => (true, true),
+        "plain" => (true, false),
+        "none" => (false, true),
+        _ => {
+            return Err(Error::InvalidArgument(format!(
+                "unknown subtitle backend `{mode}`"
+            )))
+        }
+    };
+
+    player.call(move |player| {
+        player.mpv.set_property("sub-ass", native_ass)?;
+        player.mpv.set_property("sub-visibility", visible)?;
+        player.redraw();
+        Ok(SubBackend {
+            visible: player.mpv.get_property("sub-visibility")?,
+            native_ass: player.mpv.get_property("sub-ass")?,
+        })
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubPreload {
+    pub enabled: bool,
+    /// How long reopening the current file took with the new setting, for
+    /// comparing open times; `None` if nothing was loaded.
+    pub reopen_ms: Option<u64>,
+}
+
+/// Turns subtitle preroll on or off (`demuxer-mkv-subtitle-preroll`, `yes`
+/// or `no`). With it on, mpv reads back from before each start or seek
+/// position so embedded subtitles already on screen there show up, which on
+/// files with many subtitle tracks makes opening and seeking noticeably
+/// slower. The current file is reopened so the change applies right away.
+/// Returns the applied state and how long the reopen took.
+#[tauri::command]
+pub async fn set_sub_preload(player: State<'_, PlayerHandle>, enabled: bool) -> Result<SubPreload> {
+    let took = playback::reopen_with(&player, move |player| {
+        let value = if enabled { "yes" } else { "no" };
+        player
+            .mpv
+            .set_property("demuxer-mkv-subtitle-preroll", value)?;
+        Ok(())
+    })
+    .await?;
+
+    player.call(move |player| {
+        let value: String = player.mpv.get_property("demuxer-mkv-subtitle-preroll")?;
+        Ok(SubPreload {
+            enabled: value != "no",
+            reopen_ms: took.map(|took| took.as_millis() as u64),
+        })
+    })
+}
+
+/// Font attachments of the current file, or none for streams.
+fn font_attachments(player: &PlayerHandle) -> Result<Vec<Attachment>> {
+    let path: String = player.call(|player| Ok(player.mpv.get_property("path")?))?;
+    if path.contains("://") {
+        return Ok(Vec::new());
+    }
+    let mut attachments = mkv::attachments(path.as_ref())?;
+    attachments.retain(Attachment::is_font);
+    Ok(attachments)
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbeddedFonts {
+    pub enabled: bool,
+    pub fonts: Vec<Attachment>,
+}
+
+/// Lists the fonts attached to the current file, which styled ASS subtitles
+/// (anime, karaoke) usually depend on. Only Matroska files carry them; other
+/// formats and streams give an empty list.
+#[tauri::command]
+pub async fn get_attachments(player: State<'_, PlayerHandle>) -> Result<Vec<Attachment>> {
+    font_attachments(&player)
+}
+
+/// Lets libass use the current file's attached fonts (`embeddedfonts`), on
+/// by default. Off, subtitles fall back to system fonts, for when an
+/// attached font is broken. Fonts are loaded as the file opens, so it's
+/// reopened at the same position. Returns the setting and the attached fonts.
+#[tauri::command]
+pub async fn set_embedded_fonts(
+    player: State<'_, PlayerHandle>,
+    enabled: bool,
+) -> Result<EmbeddedFonts> {
+    playback::reopen_with(&player, move |player| {
+        player.mpv.set_property("embeddedfonts", enabled)?;
+        Ok(())
+    })
+    .await?;
+
+    Ok(EmbeddedFonts {
+        enabled: player.call(|player| Ok(player.mpv.get_property("embeddedfonts")?))?,
+        fonts: font_attachments(&player)?,
+    })
+}
+
+/// Adds an external subtitle track from a local path or an `http(s)` URL,
+/// selecting it if `select` is set. Download failures and timeouts
+/// (`network-timeout`) come back as errors naming the source.
+#[tauri::command]
+pub async fn add_subtitle(
+    player: State<'_, PlayerHandle>,
+    source: String,
+    select: bool,
+) -> Result<()> {
+    tracks::check_external_track(&source)?;
+    player.call(move |player| {
+        tracks::add_external_track(player, "sub-add", &source, select)?;
+        player.redraw();
+        Ok(())
+    })
+}
+
+/// Shifts subtitles by `delta` seconds (`sub-delay`), later when positive,
+/// like mpv's `z`/`Z` keys, and shows the new delay on the OSD. The total is
+/// kept within ±600 s. Emits `mpv-sub-delay` and returns the new delay.
+#[tauri::command]
+pub async fn nudge_sub_delay(player: State<'_, PlayerHandle>, delta: f64) -> Result<f64> {
+    if !delta.is_finite() {
+        return Err(Error::InvalidArgument(format!(
+            "subtitle delay step {delta}s isn't finite"
+        )));
+    }
+
+    player.call(move |player| {
+        let delay: f64 = player.mpv.get_property("sub-delay")?;
+        let delay = (delay + delta).clamp(-MAX_SUB_DELAY, MAX_SUB_DELAY);
+        player.mpv.set_property("sub-delay", delay)?;
+        let message = format!("Subtitle delay: {:.0} ms", delay * 1000.0);
+        player.mpv.command("show-text", &[&message])?;
+        player.redraw();
+        player.emit("mpv-sub-delay", delay);
+        Ok(delay)
+    })
+}
+
+/// Sets the character encoding of text subtitle files (`sub-codepage`), for
+/// old files in a legacy encoding that otherwise come out garbled: an iconv
+/// name such as `cp1251`, `latin1` or `shift_jis`, or `auto`, the default,
+/// which detects it and falls back to UTF-8. A leading `+` forces the
+/// encoding even on files that look like UTF-8. Names are handed to mpv
+/// as they are, so a misspelled one shows up as unchanged text rather than
+/// an error.
+///
+/// This applies when a subtitle file is read, so a selected external track
+/// is reloaded (`sub-reload`) to show the change now; subtitles embedded in
+/// the video file carry their own encoding and aren't affected. Returns the
+/// applied value.
+#[tauri::command]
+pub async fn set_sub_encoding(player: State<'_, PlayerHandle>, charset: String) -> Result<String> {
+    let name = charset.strip_prefix('+').unwrap_or(&charset);
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'));
+    if !valid {
+        return Err(Error::InvalidArgument(format!(
+            "`{charset}` isn't an encoding name"
+        )));
+    }
+
+    player.call(move |player| {
+        player.mpv.set_property("sub-codepage", charset)?;
+        // Unavailable with no subtitle track selected.
+        let external = player
+            .mpv
+            .get_property("current-tracks/sub/external")
+            .unwrap_or(false);
+        if external {
+            player.mpv.command("sub-reload", &[])?;
+            player.redraw();
+        }
+        Ok(player.mpv.get_property("sub-codepage")?)
+    })
+}
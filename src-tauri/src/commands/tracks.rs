@@ -0,0 +1,234 @@
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::commands::blocking;
+use crate::error::describe_mpv_error;
+use crate::player::{Player, PlayerHandle};
+use crate::probe::{self, DecodeReport};
+use crate::{Error, Result};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubAudioLang {
+    pub alang: Vec<String>,
+    pub slang: Vec<String>,
+}
+
+/// Joins entries for a comma-separated mpv string list option.
+fn join_list(name: &str, items: &[String]) -> Result<String> {
+    if let Some(item) = items.iter().find(|item| item.contains(',')) {
+        return Err(Error::InvalidArgument(format!(
+            "{name} entry `{item}` contains a comma"
+        )));
+    }
+    Ok(items.join(","))
+}
+
+fn split_list(list: &str) -> Vec<String> {
+    list.split(',')
+        .filter(|item| !item.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+const AUTOLOAD_MODES: &[&str] = &["no", "exact", "fuzzy"];
+
+/// URL schemes `add_subtitle` and `add_audio` accept besides local paths.
+const EXTERNAL_TRACK_SCHEMES: &[&str] = &["http", "https"];
+
+/// Rejects URLs with schemes mpv shouldn't fetch sidecar tracks from. Local
+/// paths and `http(s)` URLs pass; mpv does the actual loading.
+pub fn check_external_track(source: &str) -> Result<()> {
+    let Some((scheme, _)) = source.split_once("://") else {
+        return Ok(());
+    };
+    if !EXTERNAL_TRACK_SCHEMES.contains(&scheme.to_ascii_lowercase().as_str()) {
+        return Err(Error::InvalidArgument(format!(
+            "unsupported URL scheme `{scheme}`"
+        )));
+    }
+    Ok(())
+}
+
+/// Runs `sub-add`/`audio-add` for `source`, turning mpv's bare "loading
+/// failed" into a message that says what couldn't be loaded.
+pub fn add_external_track(
+    player: &Player,
+    command: &str,
+    source: &str,
+    select: bool,
+) -> Result<()> {
+    let flag = if select { "select" } else { "auto" };
+    player.mpv.command(command, &[source, flag]).map_err(|e| {
+        Error::Mpv(format!(
+            "couldn't load {source}: {}",
+            describe_mpv_error(&e)
+        ))
+    })
+}
+
+/// Sets the preferred audio and subtitle languages, most preferred first
+/// (e.g. `["jpn", "ja"]`). mpv only consults these when it picks tracks for
+/// a newly loaded file, so the current file keeps its selection.
+#[tauri::command]
+pub async fn set_lang_prefs(
+    player: State<'_, PlayerHandle>,
+    prefs: SubAudioLang,
+) -> Result<SubAudioLang> {
+    let alang = join_list("alang", &prefs.alang)?;
+    let slang = join_list("slang", &prefs.slang)?;
+
+    player.call(move |player| {
+        player.mpv.set_property("alang", alang)?;
+        player.mpv.set_property("slang", slang)?;
+        Ok(SubAudioLang {
+            alang: split_list(&player.mpv.get_property::<String>("alang")?),
+            slang: split_list(&player.mpv.get_property::<String>("slang")?),
+        })
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Edition {
+    pub id: i64,
+    pub title: Option<String>,
+    /// Whether the file marks this as the edition to play by default.
+    pub default: bool,
+}
+
+fn read_editions(player: &Player) -> Vec<Edition> {
+    // Files without editions don't have an `edition-list` at all.
+    let Ok(list) = player.get_json("edition-list") else {
+        return Vec::new();
+    };
+    list.as_array()
+        .map(|entries| {
+            entries
+                .iter()
+                .filter_map(|entry| {
+                    Some(Edition {
+                        id: entry["id"].as_i64()?,
+                        title: entry["title"].as_str().map(str::to_owned),
+                        default: entry["default"].as_bool().unwrap_or(false),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Lists the editions of the current file (e.g. theatrical and extended
+/// cuts of an MKV), or nothing if it has none.
+#[tauri::command]
+pub async fn get_editions(player: State<'_, PlayerHandle>) -> Result<Vec<Edition>> {
+    player.call(|player| Ok(read_editions(player)))
+}
+
+/// Switches to the edition with id `index`, which makes mpv reopen the file
+/// at that edition. `mpv-edition` is emitted once it's active.
+#[tauri::command]
+pub async fn set_edition(player: State<'_, PlayerHandle>, index: i64) -> Result<i64> {
+    player.call(move |player| {
+        if !read_editions(player)
+            .iter()
+            .any(|edition| edition.id == index)
+        {
+            return Err(Error::InvalidArgument(format!("no edition {index}")));
+        }
+        player.mpv.set_property("edition", index)?;
+        Ok(index)
+    })
+}
+
+/// A subtitle or audio track that came from a separate file.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SidecarTrack {
+    pub id: i64,
+    /// `sub` or `audio`.
+    pub kind: String,
+    pub filename: String,
+}
+
+/// Lists the tracks loaded from files next to the media, rather than from
+/// inside it.
+pub fn read_sidecar_tracks(player: &Player) -> Vec<SidecarTrack> {
+    let Ok(list) = player.get_json("track-list") else {
+        return Vec::new();
+    };
+    list.as_array()
+        .map(|tracks| {
+            tracks
+                .iter()
+                .filter(|track| track["external"].as_bool() == Some(true))
+                .filter_map(|track| {
+                    Some(SidecarTrack {
+                        id: track["id"].as_i64()?,
+                        kind: track["type"].as_str()?.to_owned(),
+                        filename: track["external-filename"].as_str()?.to_owned(),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TrackCounts {
+    pub video: usize,
+    pub audio: usize,
+    pub sub: usize,
+}
+
+/// Counts the current file's tracks by type, external ones included, for
+/// badges on track menus. All zero while nothing is loaded.
+#[tauri::command]
+pub async fn get_track_counts(player: State<'_, PlayerHandle>) -> Result<TrackCounts> {
+    player.call(|player| {
+        let mut counts = TrackCounts::default();
+        let Ok(list) = player.get_json("track-list") else {
+            return Ok(counts);
+        };
+        for track in list.as_array().into_iter().flatten() {
+            match track["type"].as_str() {
+                Some("video") => counts.video += 1,
+                Some("audio") => counts.audio += 1,
+                Some("sub") => counts.sub += 1,
+                _ => {}
+            }
+        }
+        Ok(counts)
+    })
+}
+
+/// Picks up subtitle and audio files next to the media when it loads, like
+/// desktop mpv (`sub-auto` and `audio-file-auto`). `exact` wants the same
+/// name plus an extension, `fuzzy` any file containing the media's name,
+/// and `no` turns it off. The tracks attached on each load are emitted as
+/// `mpv-sidecar-tracks`. Applies from the next file loaded; returns the
+/// applied mode.
+#[tauri::command]
+pub async fn set_autoload_sidecars(
+    player: State<'_, PlayerHandle>,
+    mode: String,
+) -> Result<String> {
+    if !AUTOLOAD_MODES.contains(&mode.as_str()) {
+        return Err(Error::InvalidArgument(format!(
+            "unknown sidecar autoload mode `{mode}`"
+        )));
+    }
+
+    player.call(move |player| {
+        player.mpv.set_property("sub-auto", mode.as_str())?;
+        player.mpv.set_property("audio-file-auto", mode.as_str())?;
+        Ok(player.mpv.get_property("sub-auto")?)
+    })
+}
+
+/// Checks which streams of `path` this mpv build can decode before anything
+/// is loaded into the player, so the UI can warn about unsupported files.
+/// A separate headless mpv opens the file just long enough to read its
+/// tracks, leaving playback alone.
+#[tauri::command]
+pub async fn can_decode(path: String) -> Result<DecodeReport> {
+    blocking(move || probe::probe(&path)).await
+}
@@ -0,0 +1,808 @@
+use glow::HasContext;
+use glutin::display::{GetGlDisplay, GlDisplay};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
+use tauri::ipc::Response;
+use tauri::{Emitter, State, WebviewWindow};
+
+use crate::benchmark::{self, BenchmarkResult};
+use crate::commands::{blocking, filters, playback};
+use crate::frame_dump;
+use crate::offscreen;
+use crate::player::{Player, PlayerHandle};
+use crate::{Error, Result};
+
+/// Accepted range for `set_max_resolution`, in lines.
+const MAX_RESOLUTION_RANGE: std::ops::RangeInclusive<u32> = 144..=4320;
+
+/// Accepted range for `set_override_display_fps`, in Hz.
+const DISPLAY_FPS_RANGE: std::ops::RangeInclusive<f64> = 1.0..=1000.0;
+
+const FRAMEDROP_MODES: &[&str] = &["no", "vo", "decoder", "decoder+vo"];
+
+/// mpv's default `video-timing-offset`, in seconds.
+const DEFAULT_VIDEO_TIMING_OFFSET: f64 = 0.05;
+
+const FORCE_WINDOW_MODES: &[&str] = &["no", "yes", "immediate"];
+
+/// Packs pixels for the binary frame commands: width and height as
+/// little-endian `u32`s, then the RGBA bytes.
+fn rgba_response(width: u32, height: u32, pixels: &[u8]) -> Response {
+    let mut body = Vec::with_capacity(8 + pixels.len());
+    body.extend_from_slice(&width.to_le_bytes());
+    body.extend_from_slice(&height.to_le_bytes());
+    body.extend_from_slice(pixels);
+    Response::new(body)
+}
+
+/// Tries again to set up rendering after `mpv-render-unavailable`, for once
+/// the user has fixed their graphics driver. On success the current file is
+/// reopened at the same position so its video comes back, since mpv gave up
+/// on the video output without a render context. Returns `false` if
+/// rendering was working already; a renewed failure is an error.
+#[tauri::command]
+pub async fn retry_render(player: State<'_, PlayerHandle>) -> Result<bool> {
+    if !player.call(|player| player.retry_render())? {
+        return Ok(false);
+    }
+    playback::reopen_with(&player, |_| Ok(())).await?;
+    Ok(true)
+}
+
+/// Returns the current frame as raw pixels, sent as binary (an `ArrayBuffer`
+/// in JS) rather than JSON: width and height as little-endian `u32`s, then
+/// `width * height * 4` bytes of straight-alpha RGBA8, top row first.
+///
+/// The frame is rendered offscreen, and the window is redrawn with it
+/// afterwards, so what's on screen doesn't change. A 4K frame is ~33 MB and the
+/// synchronous `glReadPixels` stalls the render thread for tens of
+/// milliseconds, so this is meant for occasional grabs, not every frame.
+#[tauri::command]
+pub async fn grab_frame_rgba(player: State<'_, PlayerHandle>) -> Result<Response> {
+    let (width, height, pixels) = player.call(|player| offscreen::grab_frame_rgba(player))?;
+    Ok(rgba_response(width, height, &pixels))
+}
+
+/// Average brightness of the current frame, from 0 to 1, for adapting the
+/// UI to the picture. Each call renders a 64-pixel-wide copy of the frame
+/// offscreen and reads it back: little GPU work, but the readback stalls
+/// the render thread until it's done, so poll it a few times a second
+/// rather than every frame.
+/// Fails if there's no video.
+#[tauri::command]
+pub async fn get_frame_luminance(player: State<'_, PlayerHandle>) -> Result<f64> {
+    player.call(|player| offscreen::frame_luminance(player))
+}
+
+/// Switches to drawing the video on a `<canvas>` instead of the native
+/// surface, for setups where the GL surface and the webview don't stack
+/// properly. Each frame is rendered offscreen at the window size and read
+/// back, `mpv-canvas-frame` announces it, and `get_canvas_frame` fetches it
+/// for `putImageData`. The window itself is no longer drawn. Returns the
+/// new state.
+///
+/// This is far slower than the native path: every frame is a synchronous
+/// GPU readback plus an IPC copy of width * height * 4 bytes (8 MB at 1080p,
+/// 33 MB at 4K), so expect high CPU use and dropped frames beyond 720p-ish
+/// window sizes. Only use it where the native surface can't work.
+#[tauri::command]
+pub async fn set_canvas_mode(player: State<'_, PlayerHandle>, enabled: bool) -> Result<bool> {
+    player.call(move |player| {
+        player.canvas_mode = enabled;
+        if !enabled {
+            if let Some(target) = player.canvas_target.take() {
+                target.delete(&player.gl);
+            }
+            player.canvas_frame = None;
+        }
+        player.redraw();
+        Ok(enabled)
+    })
+}
+
+/// Takes the latest canvas mode frame, in the same binary layout as
+/// `grab_frame_rgba`. Each frame can be fetched once; fails if there's no
+/// new one since the last call, or canvas mode is off.
+#[tauri::command]
+pub async fn get_canvas_frame(player: State<'_, PlayerHandle>) -> Result<Response> {
+    let (width, height, pixels) = player.call(|player| {
+        player
+            .canvas_frame
+            .take()
+            .ok_or_else(|| Error::InvalidArgument("no new canvas frame".into()))
+    })?;
+
+    Ok(rgba_response(width, height, &pixels))
+}
+
+/// Spaces out redraws triggered by mpv to at least `ms` milliseconds apart
+/// (0 to 1000), so fast content can't flood the render thread with more
+/// frames than the display shows. Updates arriving in between are merged
+/// into one redraw at the end of the interval, so the newest frame is always
+/// drawn. 0, the default, redraws on every update. Returns the applied value.
+#[tauri::command]
+pub async fn set_redraw_min_interval_ms(player: State<'_, PlayerHandle>, ms: u64) -> Result<u64> {
+    if ms > 1000 {
+        return Err(Error::InvalidArgument(format!(
+            "redraw interval {ms}ms is outside 0..=1000"
+        )));
+    }
+
+    player.call(move |player| {
+        player.redraw_min_interval = Duration::from_millis(ms);
+        Ok(ms)
+    })
+}
+
+/// Chooses whether drawing a frame waits for its display time. On, the
+/// default, mpv wakes the render thread a little early and each render
+/// blocks until the frame is due, so frames land evenly at the video's rate.
+/// Off, frames are drawn as soon as they're decoded, which cuts latency and
+/// frees the render thread for other work, but makes pacing follow the
+/// update callback and so look less smooth.
+///
+/// The render API's flag for this (`block_for_target_time`) isn't exposed
+/// by libmpv2, whose last `render` argument is the Y flip, so this goes
+/// through `video-timing-offset` instead: 0 disables the wait, 0.05 s is
+/// mpv's default. That only has an effect with `video-sync=audio`, also the
+/// default. Returns whether renders block.
+#[tauri::command]
+pub async fn set_block_render(player: State<'_, PlayerHandle>, enabled: bool) -> Result<bool> {
+    player.call(move |player| {
+        let offset = if enabled {
+            DEFAULT_VIDEO_TIMING_OFFSET
+        } else {
+            0.0
+        };
+        player.mpv.set_property("video-timing-offset", offset)?;
+        let offset: f64 = player.mpv.get_property("video-timing-offset")?;
+        Ok(offset > 0.0)
+    })
+}
+
+/// Draws and presents one frame now and resolves once the GPU has finished
+/// it, for taking a screenshot of the window with known-fresh content.
+/// Unlike the regular updates, this isn't delayed or merged by
+/// `set_redraw_min_interval_ms`. With nothing loaded it presents mpv's empty
+/// frame. Fails without a render context or with video turned off, since
+/// then nothing would be drawn.
+#[tauri::command]
+pub async fn render_once(player: State<'_, PlayerHandle>) -> Result<()> {
+    player.call(|player| player.render_once())
+}
+
+/// Whether a frame of the current file has been drawn and presented since
+/// it loaded. Until then a capture would only get the background.
+#[tauri::command]
+pub async fn has_rendered_frame(player: State<'_, PlayerHandle>) -> Result<bool> {
+    player.call(|player| Ok(player.frame_rendered))
+}
+
+/// Emits `mpv-frame` with the frame's `time-pos` every time a new frame is
+/// presented, for lining HTML overlays up with exact frames. This isn't
+/// throttled: at 60 fps that is 60 IPC messages a second, each serialized
+/// and dispatched on the webview's main thread, so leave it off unless the
+/// frontend needs frame accuracy. Returns the new state.
+#[tauri::command]
+pub async fn set_frame_pts_events(player: State<'_, PlayerHandle>, enabled: bool) -> Result<bool> {
+    player.call(move |player| {
+        player.frame_pts_events = enabled;
+        Ok(enabled)
+    })
+}
+
+/// Saves the current frame to `out_path` with subtitles burned in, as styled
+/// and timed right now (`sub-delay`, `sub-scale`, ASS overrides and so on).
+/// The format follows the extension (`.png`, `.jpg`, `.webp`, ...). Returns
+/// the path written.
+///
+/// mpv renders this on the GPU, so hardware-decoded frames normally need no
+/// readback. Some interop-only decoders can't be captured at all, though;
+/// that fails with a hint to switch to a `-copy` hwdec mode.
+#[tauri::command]
+pub async fn export_frame_with_subs(
+    player: State<'_, PlayerHandle>,
+    out_path: String,
+) -> Result<String> {
+    // mpv draws the screenshot on the render thread, so the command can't be
+    // run from there; it would wait on itself. A separate client handle lets
+    // this side block instead, off the async runtime.
+    let client = player.call(|player| Ok(player.mpv.create_client(Some("export-frame"))?))?;
+
+    blocking(move || {
+        let e = match client.command("screenshot-to-file", &[&out_path, "subtitles"]) {
+            Ok(()) => return Ok(out_path),
+            Err(e) => e,
+        };
+        let hwdec: String = client.get_property("hwdec-current").unwrap_or_default();
+        if hwdec.is_empty() || hwdec == "no" || hwdec.ends_with("-copy") {
+            return Err(e.into());
+        }
+        Err(Error::Mpv(format!(
+            "can't capture `{hwdec}` frames; use `{hwdec}-copy` or software decoding to export"
+        )))
+    })
+    .await
+}
+
+/// Writes `count` consecutive decoded frames of the current file, starting
+/// exactly at `start` seconds, into `out_dir` as numbered `png` or `jpg`
+/// files, emitting `mpv-dump-progress` after each. Playback isn't touched:
+/// the frames come from a separate headless mpv. Returns how many frames
+/// were written, which is fewer than `count` if the stream ends first.
+#[tauri::command]
+pub async fn dump_frames(
+    window: WebviewWindow,
+    player: State<'_, PlayerHandle>,
+    start: f64,
+    count: u32,
+    out_dir: PathBuf,
+    format: String,
+) -> Result<u32> {
+    let path: String = player.call(|player| Ok(player.mpv.get_property("path")?))?;
+    blocking(move || {
+        frame_dump::dump_frames(&path, start, count, &out_dir, &format, |progress| {
+            window.emit("mpv-dump-progress", progress).ok();
+        })
+    })
+    .await
+}
+
+/// Makes a PNG thumbnail `width` pixels wide for every chapter of the
+/// current file, as `[chapterIndex, pngBytes]` pairs, emitting
+/// `mpv-chapter-thumbnail-progress` after each. Files without chapters give
+/// an empty list. Like `dump_frames`, the frames come from a separate
+/// headless mpv, so playback isn't touched.
+#[tauri::command]
+pub async fn generate_chapter_thumbnails(
+    window: WebviewWindow,
+    player: State<'_, PlayerHandle>,
+    width: u32,
+) -> Result<Vec<(usize, Vec<u8>)>> {
+    let path: String = player.call(|player| Ok(player.mpv.get_property("path")?))?;
+    blocking(move || {
+        frame_dump::chapter_thumbnails(&path, width, |progress| {
+            window.emit("mpv-chapter-thumbnail-progress", progress).ok();
+        })
+    })
+    .await
+}
+
+/// Times decoding the first `duration_secs` of `path` in a separate headless
+/// mpv with `hwdec` (`no` for software, the default), for comparing decoders.
+/// Emits `mpv-benchmark-progress` while running. Playback isn't touched,
+/// but both share the machine, so numbers are best taken while paused.
+#[tauri::command]
+pub async fn benchmark_decode(
+    window: WebviewWindow,
+    path: String,
+    duration_secs: f64,
+    hwdec: Option<String>,
+) -> Result<BenchmarkResult> {
+    let hwdec = hwdec.unwrap_or_else(|| "no".into());
+    blocking(move || {
+        benchmark::benchmark_decode(&path, duration_secs, &hwdec, |progress| {
+            window.emit("mpv-benchmark-progress", progress).ok();
+        })
+    })
+    .await
+}
+
+/// Switches hardware decoding live (`auto-safe`, `no`, `vaapi`, `d3d11va`,
+/// `videotoolbox`, ...) and returns the applied `hwdec` value. Names mpv
+/// doesn't know are rejected. A known API that can't be used on this machine
+/// falls back to software decoding, which shows up as `no` in the
+/// `mpv-hwdec-current` event once the decoder reinitializes.
+#[tauri::command]
+pub async fn set_hwdec(player: State<'_, PlayerHandle>, mode: String) -> Result<String> {
+    player.call(move |player| {
+        player.mpv.set_property("hwdec", mode)?;
+        Ok(player.mpv.get_property("hwdec")?)
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HwdecDevice {
+    /// DRM render node for VA-API (`vaapi-device`), empty for the default.
+    pub vaapi_device: String,
+    /// CUDA device index for NVDEC (`cuda-decode-device`), or `auto`.
+    pub cuda_device: String,
+    /// The decoder in use once the file reopened (`hwdec-current`).
+    pub hwdec_current: Option<String>,
+}
+
+/// Render nodes VA-API can open, e.g. `/dev/dri/renderD128`.
+fn drm_render_nodes() -> Vec<String> {
+    std::fs::read_dir("/dev/dri")
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok()?.path().to_str().map(str::to_owned))
+        .filter(|path| path.starts_with("/dev/dri/renderD"))
+        .collect()
+}
+
+/// Picks the device hardware decoding runs on, for machines where it lands
+/// on the wrong GPU: a DRM render node such as `/dev/dri/renderD129` for
+/// VA-API, a CUDA device index such as `1` for NVDEC, or `auto` to reset
+/// both. Render nodes are checked against `/dev/dri`; CUDA indexes can't be
+/// listed up front, so a bad one shows up as software decoding in
+/// `hwdecCurrent`. This is separate from the `gpu` startup option, which
+/// picks the GPU that renders.
+///
+/// VA-API only decodes on the chosen node in the copy-back mode
+/// (`vaapi-copy`); the zero-copy mode has to decode on the GPU that renders.
+/// The current file is reopened at the same position so the decoder is set
+/// up again. Returns the applied devices.
+#[tauri::command]
+pub async fn set_hwdec_device(player: State<'_, PlayerHandle>, id: String) -> Result<HwdecDevice> {
+    let (vaapi_device, cuda_device) = if id == "auto" {
+        (String::new(), "auto".to_owned())
+    } else if id.starts_with('/') {
+        let nodes = drm_render_nodes();
+        if !nodes.contains(&id) {
+            let available = if nodes.is_empty() {
+                "none".to_owned()
+            } else {
+                nodes.join(", ")
+            };
+            return Err(Error::InvalidArgument(format!(
+                "`{id}` isn't a DRM render node; available: {available}"
+            )));
+        }
+        (id, "auto".to_owned())
+    } else if id.parse::<u32>().is_ok() {
+        (String::new(), id)
+    } else {
+        return Err(Error::InvalidArgument(format!(
+            "unknown decode device `{id}`"
+        )));
+    };
+
+    playback::reopen_with(&player, move |player| {
+        player.mpv.set_property("vaapi-device", vaapi_device)?;
+        player.mpv.set_property("cuda-decode-device", cuda_device)?;
+        Ok(())
+    })
+    .await?;
+
+    player.call(|player| {
+        Ok(HwdecDevice {
+            vaapi_device: player.mpv.get_property("vaapi-device")?,
+            cuda_device: player.mpv.get_property("cuda-decode-device")?,
+            hwdec_current: player.mpv.get_property("hwdec-current").ok(),
+        })
+    })
+}
+
+/// Converts stereoscopic footage for a flat display. `mode` is one of `off`,
+/// `sbs-to-mono`, `tb-to-mono`, `sbs-to-anaglyph` or `tb-to-anaglyph`
+/// (side-by-side/top-bottom input, left eye or red/cyan anaglyph output).
+#[tauri::command]
+pub async fn set_3d_mode(player: State<'_, PlayerHandle>, mode: String) -> Result<String> {
+    let filter = match mode.as_str() {
+        "off" => None,
+        "sbs-to-mono" => Some("stereo3d=in=sbsl:out=ml"),
+        "tb-to-mono" => Some("stereo3d=in=abl:out=ml"),
+        "sbs-to-anaglyph" => Some("stereo3d=in=sbsl:out=arcd"),
+        "tb-to-anaglyph" => Some("stereo3d=in=abl:out=arcd"),
+        _ => return Err(Error::InvalidArgument(format!("unknown 3D mode `{mode}`"))),
+    };
+
+    player.call(move |player| {
+        filters::replace_labeled(player, "vf", "stereo3d", filter)?;
+        player.redraw();
+        Ok(mode)
+    })
+}
+
+/// Overrides the color range a file claims, for old encodes flagged wrong:
+/// `limited` (TV levels, 16-235) fixes washed-out blacks, `full` (PC levels,
+/// 0-255) fixes crushed ones, and `auto` trusts the file again. This goes
+/// through a `format` filter, so it only changes how mpv reads the frames;
+/// the output stays at `video-output-levels`. Returns the applied range.
+#[tauri::command]
+pub async fn set_color_range(player: State<'_, PlayerHandle>, range: String) -> Result<String> {
+    let filter = match range.as_str() {
+        "auto" => None,
+        "limited" => Some("format=colorlevels=limited"),
+        "full" => Some("format=colorlevels=full"),
+        _ => {
+            return Err(Error::InvalidArgument(format!(
+                "unknown color range `{range}`"
+            )))
+        }
+    };
+
+    player.call(move |player| {
+        filters::replace_labeled(player, "vf", "color-range", filter)?;
+        player.redraw();
+        Ok(range)
+    })
+}
+
+/// Turns video decoding off (`vid=no`) for audio-only playback, which also
+/// stops rendering, or back on with the track that was playing before.
+/// Emits `mpv-video-enabled` and returns the new state.
+#[tauri::command]
+pub async fn set_video_enabled(player: State<'_, PlayerHandle>, enabled: bool) -> Result<bool> {
+    player.call(move |player| {
+        if enabled == player.video_enabled {
+            return Ok(enabled);
+        }
+
+        if enabled {
+            let vid = player.disabled_vid.take().unwrap_or_else(|| "auto".into());
+            player.mpv.set_property("vid", vid)?;
+            player.video_enabled = true;
+            player.redraw();
+        } else {
+            let vid: String = player.mpv.get_property("vid")?;
+            player.mpv.set_property("vid", "no")?;
+            player.disabled_vid = (vid != "no").then_some(vid);
+            player.video_enabled = false;
+        }
+        player.emit("mpv-video-enabled", enabled);
+        Ok(enabled)
+    })
+}
+
+/// Sets when mpv brings up its video output (`force-window`): `no` only for
+/// files with video, `yes` for audio-only files too, so the OSD and an empty
+/// frame get drawn, and `immediate` as soon as loading starts rather than
+/// once the file is open. The window itself always exists here; this only
+/// decides whether mpv draws a blank frame into it before (or without) any
+/// video, which with an idle player shows up as black instead of whatever
+/// was last on screen. Returns the applied mode.
+#[tauri::command]
+pub async fn set_force_window(player: State<'_, PlayerHandle>, mode: String) -> Result<String> {
+    if !FORCE_WINDOW_MODES.contains(&mode.as_str()) {
+        return Err(Error::InvalidArgument(format!(
+            "unknown force-window mode `{mode}`"
+        )));
+    }
+
+    player.call(move |player| {
+        player.mpv.set_property("force-window", mode)?;
+        // With no video, this draws the empty frame; `redraw` copes with
+        // there being nothing to show.
+        player.redraw();
+        Ok(player.mpv.get_property("force-window")?)
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Framedrop {
+    pub mode: String,
+    /// Frames dropped at output, `None` while nothing is playing.
+    pub frame_drop_count: Option<i64>,
+    /// Frames skipped by the decoder, `None` while nothing is playing.
+    pub decoder_frame_drop_count: Option<i64>,
+}
+
+impl Framedrop {
+    fn read(player: &Player) -> Result<Self> {
+        Ok(Self {
+            mode: player.mpv.get_property("framedrop")?,
+            frame_drop_count: player.mpv.get_property("frame-drop-count").ok(),
+            decoder_frame_drop_count: player.mpv.get_property("decoder-frame-drop-count").ok(),
+        })
+    }
+}
+
+/// Chooses where mpv may drop frames to keep up on slow machines
+/// (`framedrop`: `no`, `vo`, `decoder` or `decoder+vo`). Returns the applied
+/// mode with the drop counters so far, to tell whether it helps.
+#[tauri::command]
+pub async fn set_framedrop(player: State<'_, PlayerHandle>, mode: String) -> Result<Framedrop> {
+    if !FRAMEDROP_MODES.contains(&mode.as_str()) {
+        return Err(Error::InvalidArgument(format!(
+            "unknown framedrop mode `{mode}`"
+        )));
+    }
+
+    player.call(move |player| {
+        player.mpv.set_property("framedrop", mode)?;
+        Framedrop::read(player)
+    })
+}
+
+/// Parses a CSS `#rrggbb` or `#rrggbbaa` color into RGBA bytes.
+fn parse_css_color(hex: &str) -> Result<[u8; 4]> {
+    let digits = hex
+        .strip_prefix('#')
+        .filter(|d| matches!(d.len(), 6 | 8) && d.chars().all(|c| c.is_ascii_hexdigit()))
+        .ok_or_else(|| {
+            Error::InvalidArgument(format!("`{hex}` isn't a #rrggbb or #rrggbbaa color"))
+        })?;
+    let mut rgba = [0xff; 4];
+    for (channel, pair) in rgba.iter_mut().zip(digits.as_bytes().chunks(2)) {
+        // Only ASCII hex digits got this far, so both steps succeed.
+        *channel = u8::from_str_radix(std::str::from_utf8(pair).unwrap(), 16).unwrap();
+    }
+    Ok(rgba)
+}
+
+/// Turns a CSS `#rrggbb` or `#rrggbbaa` color into mpv's `#AARRGGBB`.
+fn css_to_mpv_color(hex: &str) -> Result<String> {
+    let [r, g, b, a] = parse_css_color(hex)?;
+    Ok(format!("#{a:02X}{r:02X}{g:02X}{b:02X}"))
+}
+
+/// Colors the bars around video that doesn't fill the window, as a CSS
+/// `#rrggbb` or `#rrggbbaa` hex string. Returns the applied color in the
+/// same form.
+///
+/// mpv fills the whole target on every `render()`, so a GL clear beforehand
+/// would just be painted over; the color goes through mpv's
+/// `background-color` instead (`background` before mpv 0.38).
+#[tauri::command]
+pub async fn set_letterbox_color(player: State<'_, PlayerHandle>, hex: String) -> Result<String> {
+    let color = css_to_mpv_color(&hex)?;
+    player.call(move |player| {
+        if player
+            .mpv
+            .set_property("background-color", color.as_str())
+            .is_err()
+        {
+            player.mpv.set_property("background", color.as_str())?;
+        }
+        player.redraw();
+        Ok(hex.to_ascii_lowercase())
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClearOpts {
+    /// CSS `#rrggbb` or `#rrggbbaa` hex string.
+    pub color: String,
+    pub clear_each_frame: bool,
+}
+
+/// Sets whether the window is cleared to `color` before each `render()`.
+/// Off by default, which skips a full-window clear per frame.
+///
+/// mpv paints its own `background-color` over the whole target on every
+/// render, so while video is drawn the clear doesn't show; use
+/// `set_letterbox_color` for the bars. Where it shows is when mpv draws
+/// nothing: with video turned off by `set_video_enabled`, or without a
+/// render context, the window is cleared and presented on each update
+/// instead of keeping whatever was last drawn. Canvas mode is left alone.
+/// Returns the applied options, with the color in lowercase.
+#[tauri::command]
+pub async fn set_clear_behavior(
+    player: State<'_, PlayerHandle>,
+    opts: ClearOpts,
+) -> Result<ClearOpts> {
+    let rgba = parse_css_color(&opts.color)?.map(|channel| channel as f32 / 255.0);
+    player.call(move |player| {
+        player.clear_color = opts.clear_each_frame.then_some(rgba);
+        player.redraw();
+        Ok(ClearOpts {
+            color: opts.color.to_ascii_lowercase(),
+            clear_each_frame: opts.clear_each_frame,
+        })
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GpuContext {
+    /// The platform API and its version, e.g. `EGL 1.5` or `WGL`.
+    pub platform: String,
+    pub gl_version: String,
+    pub renderer: String,
+}
+
+/// Reports the GL context mpv renders with, as chosen by the `gpuContext`
+/// startup option. Changing it takes a restart.
+#[tauri::command]
+pub async fn get_gpu_context(player: State<'_, PlayerHandle>) -> Result<GpuContext> {
+    player.call(|player| {
+        let gl = &player.gl;
+        Ok(GpuContext {
+            platform: player.context.display().version_string(),
+            gl_version: unsafe { gl.get_parameter_string(glow::VERSION) },
+            renderer: unsafe { gl.get_parameter_string(glow::RENDERER) },
+        })
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActiveGpu {
+    pub vendor: String,
+    pub renderer: String,
+    /// The `gpu` startup option, if set. The OS or driver may not have
+    /// honoured it; `renderer` says what it actually picked.
+    pub requested: Option<String>,
+}
+
+/// Reports which GPU the render context ended up on.
+#[tauri::command]
+pub async fn get_active_gpu(player: State<'_, PlayerHandle>) -> Result<ActiveGpu> {
+    player.call(|player| {
+        let gl = &player.gl;
+        Ok(ActiveGpu {
+            vendor: unsafe { gl.get_parameter_string(glow::VENDOR) },
+            renderer: unsafe { gl.get_parameter_string(glow::RENDERER) },
+            requested: player.requested_gpu.clone(),
+        })
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenderTexture {
+    /// GL name of the RGBA8 `TEXTURE_2D` mpv renders into.
+    pub texture: u32,
+    /// GL name of the framebuffer it's attached to.
+    pub fbo: u32,
+    pub width: i32,
+    pub height: i32,
+}
+
+/// Reports the texture mpv renders into when the `renderToTexture` startup
+/// option is set, so another renderer can sample the video directly; `None`
+/// without the option, or before the first frame. The texture is replaced
+/// whenever the window is resized, so re-query after a resize.
+///
+/// The ids are only valid in the player's GL context and in contexts created
+/// to share objects with it; in an unrelated context they name nothing or
+/// something else. Rows run
+/// bottom-up as usual in GL, and mpv redraws the texture on the render
+/// thread every frame, so a consumer on another context has to sync with it
+/// (e.g. a fence) before sampling.
+#[tauri::command]
+pub async fn get_render_texture(player: State<'_, PlayerHandle>) -> Result<Option<RenderTexture>> {
+    player.call(|player| {
+        Ok(player.render_texture.as_ref().map(|target| RenderTexture {
+            texture: target.texture_id(),
+            fbo: target.id() as u32,
+            width: target.width,
+            height: target.height,
+        }))
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GlCaps {
+    pub gl_version: String,
+    /// Largest texture width or height, in pixels.
+    pub max_texture_size: i32,
+    /// Whether half-float framebuffers work, which mpv's HDR and most
+    /// custom shaders need for their intermediate passes.
+    pub float_fbo: bool,
+}
+
+impl GlCaps {
+    fn read(player: &Player) -> Self {
+        let gl = &player.gl;
+        // Probing is the only reliable check: drivers advertise float
+        // textures they can't actually render to.
+        let float_fbo =
+            offscreen::Framebuffer::with_format(gl, 16, 16, glow::RGBA16F, glow::HALF_FLOAT)
+                .map(|framebuffer| framebuffer.delete(gl))
+                .is_ok();
+        Self {
+            gl_version: unsafe { gl.get_parameter_string(glow::VERSION) },
+            max_texture_size: unsafe { gl.get_parameter_i32(glow::MAX_TEXTURE_SIZE) },
+            float_fbo,
+        }
+    }
+}
+
+/// Reports what the GL context can do, so the UI can hide options the GPU
+/// can't handle. Read once and cached, since it can't change at runtime.
+#[tauri::command]
+pub async fn get_gl_caps(player: State<'_, PlayerHandle>) -> Result<GlCaps> {
+    player.call(|player| {
+        if let Some(caps) = &player.gl_caps {
+            return Ok(caps.clone());
+        }
+        let caps = GlCaps::read(player);
+        player.gl_caps = Some(caps.clone());
+        Ok(caps)
+    })
+}
+
+/// Downscales video taller than `height` lines (keeping the aspect ratio)
+/// before it reaches the GPU, so 4K files stay playable on weak integrated
+/// graphics; `None` removes the cap. `height` must be even and within
+/// 144..=4320. Scaling runs on the CPU, so hardware-decoded frames get copied
+/// back first. Returns the applied cap.
+#[tauri::command]
+pub async fn set_max_resolution(
+    player: State<'_, PlayerHandle>,
+    height: Option<u32>,
+) -> Result<Option<u32>> {
+    if let Some(height) = height {
+        if !MAX_RESOLUTION_RANGE.contains(&height) || height % 2 != 0 {
+            return Err(Error::InvalidArgument(format!(
+                "max resolution {height} must be even and within 144..=4320"
+            )));
+        }
+    }
+    // The bracket quoting keeps mpv from splitting the graph at the comma.
+    let filter = height.map(|height| format!("lavfi=[scale=w=-2:h='min(ih,{height})']"));
+
+    player.call(move |player| {
+        filters::replace_labeled(player, "vf", "max-resolution", filter.as_deref())?;
+        player.redraw();
+        Ok(height)
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DisplayFps {
+    /// The refresh rate mpv assumes, `None` while it doesn't know one.
+    pub display_fps: Option<f64>,
+    /// The rate measured from actual buffer swaps.
+    pub estimated_display_fps: Option<f64>,
+    /// The rate forced with `set_override_display_fps`, if any.
+    pub override_display_fps: Option<f64>,
+    /// `display-resample` and the other `display-*` modes time playback
+    /// to `display_fps`, so a wrong value shows up as judder there.
+    pub video_sync: String,
+}
+
+impl DisplayFps {
+    fn read(player: &Player) -> Result<Self> {
+        let positive = |name| {
+            player
+                .mpv
+                .get_property::<f64>(name)
+                .ok()
+                .filter(|fps| *fps > 0.0)
+        };
+        Ok(Self {
+            display_fps: positive("display-fps"),
+            estimated_display_fps: positive("estimated-display-fps"),
+            override_display_fps: positive("override-display-fps"),
+            video_sync: player.mpv.get_property("video-sync")?,
+        })
+    }
+}
+
+/// Reports the display refresh rate mpv works with. mpv can't query the
+/// monitor through the render API, so `displayFps` stays unset until mpv
+/// has measured enough swaps or an override is set.
+#[tauri::command]
+pub async fn get_display_fps(player: State<'_, PlayerHandle>) -> Result<DisplayFps> {
+    player.call(|player| DisplayFps::read(player))
+}
+
+/// Forces the display refresh rate to `fps` (`override-display-fps`, 1 to
+/// 1000 Hz) for displays mpv measures wrong; `None` goes back to measuring.
+/// Returns the display rates afterwards.
+#[tauri::command]
+pub async fn set_override_display_fps(
+    player: State<'_, PlayerHandle>,
+    fps: Option<f64>,
+) -> Result<DisplayFps> {
+    if let Some(fps) = fps {
+        if !DISPLAY_FPS_RANGE.contains(&fps) {
+            return Err(Error::InvalidArgument(format!(
+                "display fps {fps} is outside 1..=1000"
+            )));
+        }
+    }
+
+    player.call(move |player| {
+        let fps = fps.unwrap_or(0.0);
+        // Before mpv 0.37 the option was `display-fps` itself.
+        if player
+            .mpv
+            .set_property("override-display-fps", fps)
+            .is_err()
+        {
+            player.mpv.set_property("display-fps", fps)?;
+        }
+        DisplayFps::read(player)
+    })
+}
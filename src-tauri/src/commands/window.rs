@@ -0,0 +1,182 @@
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, State, WindowEvent};
+
+use crate::mirror::Mirror;
+use crate::player::PlayerHandle;
+use crate::{Error, Result};
+
+/// How often the cursor position is sampled for auto-hide.
+const CURSOR_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How often `set_release_gl_on_minimize` checks whether the window is
+/// minimized. Not every platform sends a window event for it.
+const MINIMIZE_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Hides the OS cursor after it has been still over the window for `ms`
+/// milliseconds and shows it again on the next movement; 0 turns auto-hide
+/// off. mpv's own `cursor-autohide` has no effect here since mpv doesn't own
+/// the window, so this polls the cursor position instead. Returns the applied
+/// timeout.
+#[tauri::command]
+pub async fn set_cursor_autohide(player: State<'_, PlayerHandle>, ms: u64) -> Result<u64> {
+    player.call(move |player| {
+        player.window.set_cursor_visible(true).ok();
+        if ms == 0 {
+            player.cancel_timer("cursor-autohide");
+            return Ok(0);
+        }
+
+        let timeout = Duration::from_millis(ms);
+        let mut last_position = None;
+        let mut last_moved = Instant::now();
+        let mut hidden = false;
+        player.set_timer("cursor-autohide", CURSOR_POLL_INTERVAL, move |player| {
+            let position = player.window.cursor_position().ok();
+            if position != last_position {
+                last_position = position;
+                last_moved = Instant::now();
+                if hidden {
+                    hidden = false;
+                    player.window.set_cursor_visible(true).ok();
+                }
+            } else if !hidden && last_moved.elapsed() >= timeout {
+                hidden = true;
+                player.window.set_cursor_visible(false).ok();
+            }
+            true
+        });
+        Ok(ms)
+    })
+}
+
+/// Removes the window's decorations (title bar and border) or puts them
+/// back, then resizes and redraws the video surface so it matches the new
+/// client area. The platform applies the change asynchronously; the
+/// `Resized` that follows on most platforms triggers another resize, so the
+/// surface ends up right either way. Returns the new state.
+#[tauri::command]
+pub async fn set_borderless(player: State<'_, PlayerHandle>, enabled: bool) -> Result<bool> {
+    player.call(move |player| {
+        player
+            .window
+            .set_decorations(!enabled)
+            .map_err(|e| Error::Window(e.to_string()))?;
+        player.resize();
+        Ok(enabled)
+    })
+}
+
+/// Mirrors the video into the window labelled `label`, e.g. a fullscreen
+/// window on a projector, or stops mirroring when `label` is `None`. The
+/// window has to exist already. Each frame is presented in the main window
+/// first, then in the mirror. Closing the mirror window stops mirroring.
+#[tauri::command]
+pub async fn mirror_to_window(
+    app: AppHandle,
+    player: State<'_, PlayerHandle>,
+    label: Option<String>,
+) -> Result<Option<String>> {
+    let Some(label) = label else {
+        player.call(|player| {
+            player.mirror = None;
+            Ok(())
+        })?;
+        return Ok(None);
+    };
+    if label == "main" {
+        return Err(Error::InvalidArgument(
+            "can't mirror the main window into itself".into(),
+        ));
+    }
+    let window = app
+        .get_webview_window(&label)
+        .ok_or_else(|| Error::InvalidArgument(format!("no window labelled `{label}`")))?;
+
+    let handle = player.inner().clone();
+    let newly_mirrored = player.call({
+        let window = window.clone();
+        move |player| {
+            if player
+                .mirror
+                .as_ref()
+                .is_some_and(|mirror| mirror.window.label() == window.label())
+            {
+                return Ok(false);
+            }
+            // Drop the old surface before creating the new one.
+            player.mirror = None;
+            player.mirror = Some(Mirror::new(player, window)?);
+            player.redraw();
+            Ok(true)
+        }
+    })?;
+
+    if newly_mirrored {
+        let close_window = window.clone();
+        window.on_window_event(move |event| match event {
+            WindowEvent::Resized(_) => {
+                handle
+                    .post(|player| {
+                        player.redraw();
+                    })
+                    .ok();
+            }
+            WindowEvent::CloseRequested { api, .. } => {
+                // The surface has to go before the window it draws to.
+                api.prevent_close();
+                let window = close_window.clone();
+                let sent = handle.post(move |player| {
+                    if player
+                        .mirror
+                        .as_ref()
+                        .is_some_and(|mirror| mirror.window.label() == window.label())
+                    {
+                        player.mirror = None;
+                    }
+                    window.destroy().ok();
+                });
+                if sent.is_err() {
+                    close_window.destroy().ok();
+                }
+            }
+            _ => {}
+        });
+    }
+    Ok(Some(label))
+}
+
+/// Stops rendering while the window is minimized and frees the GPU memory
+/// that drawing holds, to save power and VRAM on laptops: the render
+/// texture and canvas targets are deleted and the window surface is shrunk
+/// to 1x1, where the platform allows. `mpv-gl-released` is emitted on
+/// minimize and `mpv-gl-restored` once the window is back, when the first
+/// frame is drawn straight away. Playback and decoding carry on meanwhile.
+///
+/// The surface isn't destroyed outright: the GL context has to stay
+/// current on one, since mpv may run GL work at any time, and with no
+/// surface that would fail. Turning this off restores rendering if it's
+/// released. Returns the new state.
+#[tauri::command]
+pub async fn set_release_gl_on_minimize(
+    player: State<'_, PlayerHandle>,
+    enabled: bool,
+) -> Result<bool> {
+    player.call(move |player| {
+        if !enabled {
+            player.cancel_timer("release-gl");
+            player.restore_gl();
+            return Ok(false);
+        }
+
+        player.set_timer("release-gl", MINIMIZE_POLL_INTERVAL, |player| {
+            match player.window.is_minimized() {
+                Ok(true) => player.release_gl(),
+                Ok(false) => player.restore_gl(),
+                // The window is being torn down.
+                Err(_) => {}
+            }
+            true
+        });
+        Ok(true)
+    })
+}
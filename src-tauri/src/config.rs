@@ -0,0 +1,73 @@
+use serde::Deserialize;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// Startup options read from `player.json` in the app config dir. Everything
+/// here has to be known before mpv or the GL context is created; settings
+/// that can change at runtime are commands instead.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PlayerConfig {
+    /// mpv `ao`, e.g. `pipewire,pulse,alsa` to try each in order.
+    pub ao: Option<String>,
+    /// Which GL platform API glutin creates the context with: `egl`, `wgl`
+    /// or `angle` (EGL via ANGLE) on Windows, `egl` or `glx` on X11. Unset
+    /// or `auto` tries the platform's usual order. mpv's own `gpu-context`
+    /// and `gpu-api` don't apply, since mpv renders into our context.
+    pub gpu_context: Option<String>,
+    /// GPU preference on hybrid graphics: `high-performance` or
+    /// `low-power`, or on Linux a Mesa `DRI_PRIME` adapter. Unset leaves it
+    /// to the OS.
+    pub gpu: Option<String>,
+    /// Render into a texture that `get_render_texture` exposes, copying it
+    /// to the window afterwards, instead of straight into the window.
+    pub render_to_texture: bool,
+    /// Raise the render thread's OS priority to reduce stutter on busy
+    /// systems. Off by default since it takes time away from everything else.
+    pub raise_render_thread_priority: bool,
+    /// mpv's `config-dir`, where `~~/` paths, scripts and `script-opts`
+    /// live. Created if missing. Unset uses mpv's per-user default.
+    pub config_dir: Option<PathBuf>,
+    /// mpv's `cache-dir`, for the shader and ICC caches.
+    pub cache_dir: Option<PathBuf>,
+    /// mpv's `watch-later-dir`, for `write-watch-later-config` state.
+    pub watch_later_dir: Option<PathBuf>,
+}
+
+impl PlayerConfig {
+    /// Falls back to defaults when the file is missing or invalid, so a bad
+    /// config never keeps the player from starting.
+    pub fn load(path: &Path) -> Self {
+        match fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                println!("Ignoring invalid {}: {}", path.display(), e);
+                Self::default()
+            }),
+            Err(e) if e.kind() == ErrorKind::NotFound => Self::default(),
+            Err(e) => {
+                println!("Failed to read {}: {}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+}
+
+/// Creates `dir` if needed and returns its absolute path, or `None` (after
+/// logging why) if it can't be used, in which case mpv's default applies.
+pub fn prepare_dir(name: &str, dir: &Path) -> Option<String> {
+    let resolved = fs::create_dir_all(dir).and_then(|()| dir.canonicalize());
+    match resolved {
+        Ok(path) => match path.to_str() {
+            Some(path) => Some(path.to_owned()),
+            None => {
+                println!("Ignoring {name} {}: not UTF-8", path.display());
+                None
+            }
+        },
+        Err(e) => {
+            println!("Ignoring {name} {}: {}", dir.display(), e);
+            None
+        }
+    }
+}
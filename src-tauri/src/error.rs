@@ -0,0 +1,133 @@
+use libmpv2::mpv_error;
+use serde::{Serialize, Serializer};
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("mpv: {0}")]
+    Mpv(String),
+    #[error("OpenGL: {0}")]
+    Gl(String),
+    #[error("window: {0}")]
+    Window(String),
+    #[error("I/O: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("invalid argument: {0}")]
+    InvalidArgument(String),
+    #[error("the render thread is not running")]
+    PlayerUnavailable,
+    #[error("timed out waiting for {0}")]
+    Timeout(String),
+    #[error("background task: {0}")]
+    Task(String),
+}
+
+// `libmpv2::Error` holds an `Rc`, so it can't cross back from the render
+// thread as-is. Flatten it into a readable message instead.
+impl From<libmpv2::Error> for Error {
+    fn from(err: libmpv2::Error) -> Self {
+        Error::Mpv(describe_mpv_error(&err))
+    }
+}
+
+pub fn describe_mpv_error(err: &libmpv2::Error) -> String {
+    match err {
+        libmpv2::Error::Loadfile { error } => {
+            format!("loadfile failed: {}", describe_mpv_error(error))
+        }
+        libmpv2::Error::VersionMismatch { linked, loaded } => {
+            format!("client API version mismatch (linked {linked:#x}, loaded {loaded:#x})")
+        }
+        libmpv2::Error::InvalidUtf8 => "invalid UTF-8".into(),
+        libmpv2::Error::Null => "unexpected NUL byte or null pointer".into(),
+        libmpv2::Error::Raw(code) => match *code {
+            mpv_error::EventQueueFull => "event queue full".into(),
+            mpv_error::NoMem => "out of memory".into(),
+            mpv_error::Uninitialized => "core not initialized".into(),
+            mpv_error::InvalidParameter => "invalid parameter".into(),
+            mpv_error::OptionNotFound => "option not found".into(),
+            mpv_error::OptionFormat => "unsupported option format".into(),
+            mpv_error::OptionError => "error setting option".into(),
+            mpv_error::PropertyNotFound => "property not found".into(),
+            mpv_error::PropertyFormat => "unsupported property format".into(),
+            mpv_error::PropertyUnavailable => "property unavailable".into(),
+            mpv_error::PropertyError => "error accessing property".into(),
+            mpv_error::Command => "error running command".into(),
+            mpv_error::LoadingFailed => "loading failed".into(),
+            mpv_error::AoInitFailed => "audio output initialization failed".into(),
+            mpv_error::VoInitFailed => "video output initialization failed".into(),
+            mpv_error::NothingToPlay => "no audio or video data to play".into(),
+            mpv_error::UnknownFormat => "unrecognized file format".into(),
+            mpv_error::Unsupported => "not supported".into(),
+            mpv_error::NotImplemented => "not implemented".into(),
+            mpv_error::Generic => "unspecified error".into(),
+            code => format!("error {code}"),
+        },
+    }
+}
+
+impl From<glutin::error::Error> for Error {
+    fn from(err: glutin::error::Error) -> Self {
+        Error::Gl(err.to_string())
+    }
+}
+
+/// Payload of `mpv-error`: a failure on the render thread that no command
+/// is waiting to hear about, classified so the UI can suggest a fix.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorReport {
+    /// `gl`, `codec`, `io` or `other`.
+    pub kind: &'static str,
+    pub message: String,
+    /// Something the user can try, if there's an obvious candidate.
+    pub hint: Option<&'static str>,
+}
+
+impl ErrorReport {
+    /// Describes `err`, which happened while doing `context`.
+    pub fn new(context: &str, err: &Error) -> Self {
+        let (kind, hint) = match err {
+            Error::Gl(_) => (
+                "gl",
+                Some("try another gpuContext startup option or update the graphics driver"),
+            ),
+            Error::Io(_) => ("io", Some("check that the file exists and is readable")),
+            Error::Mpv(message) if message.contains("video output initialization") => {
+                ("gl", Some("try disabling hardware decoding"))
+            }
+            Error::Mpv(message)
+                if message.contains("unrecognized file format")
+                    || message.contains("no audio or video data") =>
+            {
+                (
+                    "codec",
+                    Some("the file may be damaged or use an unsupported codec"),
+                )
+            }
+            Error::Mpv(message) if message.contains("loading failed") => {
+                ("io", Some("check the path or network connection"))
+            }
+            Error::Mpv(message) if message.contains("audio output initialization") => (
+                "other",
+                Some("try another audio output with the ao startup option"),
+            ),
+            _ => ("other", None),
+        };
+        Self {
+            kind,
+            message: format!("{context}: {err}"),
+            hint,
+        }
+    }
+}
+
+// Commands return this straight to the frontend, which only needs the message.
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
@@ -0,0 +1,133 @@
+use libmpv2::Mpv;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use crate::player::Player;
+use crate::Result;
+
+/// Settings remembered for one file. Fields mpv couldn't report when the
+/// file was saved are left out and not touched when it loads again.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub volume: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sub_delay: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
+}
+
+impl FileSettings {
+    fn read(mpv: &Mpv) -> Self {
+        Self {
+            volume: mpv.get_property("volume").ok(),
+            sub_delay: mpv.get_property("sub-delay").ok(),
+            aid: mpv.get_property("aid").ok(),
+            sid: mpv.get_property("sid").ok(),
+        }
+    }
+
+    fn apply(&self, mpv: &Mpv) -> Result<()> {
+        if let Some(volume) = self.volume {
+            mpv.set_property("volume", volume)?;
+        }
+        if let Some(sub_delay) = self.sub_delay {
+            mpv.set_property("sub-delay", sub_delay)?;
+        }
+        if let Some(aid) = &self.aid {
+            mpv.set_property("aid", aid.as_str())?;
+        }
+        if let Some(sid) = &self.sid {
+            mpv.set_property("sid", sid.as_str())?;
+        }
+        Ok(())
+    }
+}
+
+/// A JSON file mapping mpv's `path` for each file to its `FileSettings`.
+pub struct FileSettingsStore {
+    path: PathBuf,
+    entries: HashMap<String, FileSettings>,
+}
+
+impl FileSettingsStore {
+    /// Loads the store, starting empty if the file is missing or unreadable.
+    /// A corrupt store is overwritten on the next save rather than blocking
+    /// playback.
+    pub fn open(path: PathBuf) -> Self {
+        let entries = match fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                println!("Ignoring corrupt {}: {}", path.display(), e);
+                HashMap::new()
+            }),
+            Err(e) if e.kind() == ErrorKind::NotFound => HashMap::new(),
+            Err(e) => {
+                println!("Failed to read {}: {}", path.display(), e);
+                HashMap::new()
+            }
+        };
+        Self { path, entries }
+    }
+
+    pub fn get(&self, file: &str) -> Option<&FileSettings> {
+        self.entries.get(file)
+    }
+
+    pub fn insert(&mut self, file: String, settings: FileSettings) -> Result<()> {
+        self.entries.insert(file, settings);
+        self.save()
+    }
+
+    fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        // Write to a temp file first so a crash mid-write can't corrupt the store.
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(&self.entries)?)?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+impl Player {
+    /// Remembers the loaded file's settings. Returns `None` if nothing is
+    /// loaded or the store is disabled.
+    pub fn save_file_settings(&mut self) -> Result<Option<FileSettings>> {
+        let Some(store) = self.file_settings.as_mut() else {
+            return Ok(None);
+        };
+        let Ok(file) = self.mpv.get_property::<String>("path") else {
+            return Ok(None);
+        };
+        // The "path" of a `load_bytes` source is the whole file as hex.
+        if file.starts_with("hex://") {
+            return Ok(None);
+        }
+
+        let settings = FileSettings::read(&self.mpv);
+        store.insert(file, settings.clone())?;
+        Ok(Some(settings))
+    }
+
+    pub fn apply_file_settings(&mut self) {
+        let Some(store) = self.file_settings.as_ref() else {
+            return;
+        };
+        let Ok(file) = self.mpv.get_property::<String>("path") else {
+            return;
+        };
+
+        if let Some(settings) = store.get(&file) {
+            if let Err(e) = settings.apply(&self.mpv) {
+                println!("Failed to apply settings for {}: {}", file, e);
+            }
+        }
+    }
+}
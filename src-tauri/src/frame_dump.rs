@@ -0,0 +1,193 @@
+use libmpv2::events::{Event, PropertyData};
+use libmpv2::{Format, Mpv};
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::{Error, Result};
+
+/// How long to wait for the file to open, or for a single frame step.
+const STEP_TIMEOUT: Duration = Duration::from_secs(10);
+
+const FORMATS: &[&str] = &["png", "jpg"];
+
+/// Accepted thumbnail widths for `chapter_thumbnails`, in pixels.
+const THUMBNAIL_WIDTHS: std::ops::RangeInclusive<u32> = 16..=3840;
+
+static NEXT_THUMBNAIL_CALL: AtomicU32 = AtomicU32::new(0);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DumpProgress {
+    pub written: u32,
+    pub count: u32,
+}
+
+/// Pumps `mpv`'s events until `done` accepts one. Fails if the file ends
+/// (with `keep-open` that only happens on errors) or nothing comes in time,
+/// naming `what` was awaited in the timeout error.
+pub fn wait_for(mpv: &mut Mpv, what: &str, mut done: impl FnMut(&Event) -> bool) -> Result<()> {
+    let deadline = Instant::now() + STEP_TIMEOUT;
+    loop {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        if timeout.is_zero() {
+            return Err(Error::Timeout(what.into()));
+        }
+        match mpv.wait_event(timeout.as_secs_f64()) {
+            Some(Ok(Event::EndFile(_))) => {
+                return Err(Error::Mpv("the file ended unexpectedly".into()))
+            }
+            Some(Ok(event)) if done(&event) => return Ok(()),
+            Some(Err(e)) => return Err(e.into()),
+            _ => {}
+        }
+    }
+}
+
+/// Drops whatever events are queued, so stale changes aren't mistaken for
+/// the next step.
+fn drain_events(mpv: &mut Mpv) {
+    while mpv.wait_event(0.0).is_some() {}
+}
+
+/// Writes `count` consecutive frames of `path` from `start` (seconds) into
+/// `out_dir` as `frame-000001.<format>` and so on, calling `progress` after
+/// each. Stops early at the end of the stream. Returns how many were written.
+///
+/// This runs its own headless mpv (`vo=null`), paused, and frame-steps it,
+/// so the visible player is never seeked or paused. Call it off the render
+/// thread; it blocks for as long as decoding takes.
+pub fn dump_frames(
+    path: &str,
+    start: f64,
+    count: u32,
+    out_dir: &Path,
+    format: &str,
+    mut progress: impl FnMut(DumpProgress),
+) -> Result<u32> {
+    if !FORMATS.contains(&format) {
+        return Err(Error::InvalidArgument(format!(
+            "unknown frame format `{format}`"
+        )));
+    }
+    fs::create_dir_all(out_dir)?;
+
+    let mut mpv = Mpv::with_initializer(|init| {
+        init.set_option("vo", "null")?;
+        init.set_option("ao", "null")?;
+        init.set_option("pause", true)?;
+        // Hold the last frame at the end instead of unloading the file.
+        init.set_option("keep-open", "always")?;
+        init.set_option("hr-seek", "yes")?;
+        init.set_option("screenshot-format", format)?;
+        init.set_option("start", start.to_string().as_str())?;
+        Ok(())
+    })?;
+    mpv.observe_property("time-pos", Format::Double, 0)?;
+    mpv.observe_property("eof-reached", Format::Flag, 1)?;
+    mpv.command("loadfile", &[path, "replace"])?;
+    wait_for(&mut mpv, "the file to open", |event| {
+        matches!(event, Event::PlaybackRestart)
+    })?;
+
+    let mut written = 0;
+    while written < count {
+        drain_events(&mut mpv);
+        let file = out_dir.join(format!("frame-{:06}.{format}", written + 1));
+        let file = file
+            .to_str()
+            .ok_or_else(|| Error::InvalidArgument(format!("{} isn't UTF-8", file.display())))?;
+        mpv.command("screenshot-to-file", &[file, "video"])?;
+        written += 1;
+        progress(DumpProgress { written, count });
+
+        if written == count {
+            break;
+        }
+        mpv.command("frame-step", &[])?;
+        // Stepping past the last frame leaves the position alone and sets
+        // `eof-reached` instead.
+        wait_for(&mut mpv, "the next decoded frame", |event| {
+            matches!(
+                event,
+                Event::PropertyChange {
+                    name: "time-pos",
+                    ..
+                } | Event::PropertyChange {
+                    name: "eof-reached",
+                    change: PropertyData::Flag(true),
+                    ..
+                }
+            )
+        })?;
+        if mpv.get_property("eof-reached").unwrap_or(false) {
+            break;
+        }
+    }
+    Ok(written)
+}
+
+/// Renders one PNG thumbnail `width` pixels wide (height following the
+/// aspect ratio) at the start of each chapter of `path`, calling `progress`
+/// after each. Returns `(chapter index, PNG bytes)` pairs in chapter order,
+/// or nothing for files without chapters.
+///
+/// Like `dump_frames`, this uses its own headless mpv, so the player isn't
+/// disturbed, and blocks until every chapter has been seeked to and decoded.
+pub fn chapter_thumbnails(
+    path: &str,
+    width: u32,
+    mut progress: impl FnMut(DumpProgress),
+) -> Result<Vec<(usize, Vec<u8>)>> {
+    if !THUMBNAIL_WIDTHS.contains(&width) {
+        return Err(Error::InvalidArgument(format!(
+            "thumbnail width {width} is outside 16..=3840"
+        )));
+    }
+
+    let mut mpv = Mpv::with_initializer(|init| {
+        init.set_option("vo", "null")?;
+        init.set_option("ao", "null")?;
+        init.set_option("pause", true)?;
+        init.set_option("keep-open", "always")?;
+        init.set_option("hr-seek", "yes")?;
+        init.set_option("screenshot-format", "png")?;
+        // `video` screenshots are taken after the filter chain.
+        init.set_option("vf", format!("scale=w={width}:h=-2").as_str())?;
+        Ok(())
+    })?;
+    mpv.command("loadfile", &[path, "replace"])?;
+    wait_for(&mut mpv, "the file to open", |event| {
+        matches!(event, Event::PlaybackRestart)
+    })?;
+
+    let count = mpv.get_property::<i64>("chapters").unwrap_or(0).max(0) as u32;
+    // Unique per call, in case two run at once.
+    let call = NEXT_THUMBNAIL_CALL.fetch_add(1, Ordering::Relaxed);
+    let file = std::env::temp_dir().join(format!("mpv-chapter-{}-{call}.png", std::process::id()));
+    let file_str = file
+        .to_str()
+        .ok_or_else(|| Error::InvalidArgument(format!("{} isn't UTF-8", file.display())))?;
+
+    let mut thumbnails = Vec::with_capacity(count as usize);
+    for index in 0..count {
+        let time: f64 = mpv.get_property(&format!("chapter-list/{index}/time"))?;
+        drain_events(&mut mpv);
+        mpv.command("seek", &[&time.to_string(), "absolute+exact"])?;
+        wait_for(&mut mpv, "the chapter seek", |event| {
+            matches!(event, Event::PlaybackRestart)
+        })?;
+
+        mpv.command("screenshot-to-file", &[file_str, "video"])?;
+        let png = fs::read(&file);
+        fs::remove_file(&file).ok();
+        thumbnails.push((index as usize, png?));
+        progress(DumpProgress {
+            written: index + 1,
+            count,
+        });
+    }
+    Ok(thumbnails)
+}
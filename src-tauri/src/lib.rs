@@ -1,18 +1,38 @@
+mod benchmark;
+mod commands;
+mod config;
+mod error;
+mod file_settings;
+mod frame_dump;
+mod mirror;
+mod mkv;
+mod offscreen;
+mod player;
+mod probe;
+mod render_params;
+mod waveform;
+
 use glutin::context::NotCurrentGlContext;
 use glutin::display::DisplayApiPreference;
 use glutin::prelude::GlDisplay;
-use glutin::surface::{GlSurface, WindowSurface};
-use libmpv2::events::Event;
-use raw_window_handle::{HasDisplayHandle, HasWindowHandle};
+use glutin::surface::WindowSurface;
+use raw_window_handle::{HasDisplayHandle, HasWindowHandle, RawDisplayHandle, RawWindowHandle};
 use std::ffi::{c_void, CString};
 use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
 use std::{num::NonZeroU32, thread};
-use tauri::Manager;
+use tauri::{Emitter, Manager};
+use thread_priority::ThreadPriority;
+
+use libmpv2::{render::RenderContext, Mpv};
 
-use libmpv2::{
-    render::{OpenGLInitParams, RenderContext, RenderParam, RenderParamApiType},
-    Mpv,
-};
+use config::{prepare_dir, PlayerConfig};
+use error::ErrorReport;
+pub use error::{Error, Result};
+#[cfg(all(unix, not(target_os = "macos")))]
+pub use offscreen::render_offscreen;
+use player::{MpvThreadEvent, Player, PlayerHandle};
+use render_params::RenderParams;
 
 pub trait GlWindow {
     fn build_surface_attributes(
@@ -27,11 +47,12 @@ impl GlWindow for tauri::WebviewWindow {
         builder: glutin::surface::SurfaceAttributesBuilder<WindowSurface>,
     ) -> Result<glutin::surface::SurfaceAttributes<WindowSurface>, raw_window_handle::HandleError>
     {
+        // A zero-sized (e.g. minimized) window can't back a surface yet.
         let (w, h) = self
             .inner_size()
-            .unwrap()
-            .non_zero()
-            .expect("invalid zero inner size");
+            .ok()
+            .and_then(|size| size.non_zero())
+            .ok_or(raw_window_handle::HandleError::Unavailable)?;
         let handle = self.window_handle()?.as_raw();
         Ok(builder.build(handle, w, h))
     }
@@ -56,10 +77,259 @@ fn get_proc_address(display: &Arc<glutin::display::Display>, name: &str) -> *mut
     }
 }
 
-#[derive(Debug)]
-enum MpvThreadEvent {
-    Redraw,
-    MpvEvents,
+/// Borrows the raw handle `RenderContext::new` wants. An `Mpv` only exists
+/// once `mpv_create` and `mpv_initialize` have succeeded (`with_initializer`
+/// returns `Err` otherwise, including for a null handle), and its `ctx` stays
+/// a valid `NonNull` until it is dropped, which the borrow rules out here.
+fn mpv_handle(mpv: &mut Mpv) -> &mut libmpv2_sys::mpv_handle {
+    unsafe { mpv.ctx.as_mut() }
+}
+
+/// Creates mpv's render context on `display`'s current GL context, with
+/// updates sent to the render loop as `Redraw`.
+pub(crate) fn create_render_context(
+    mpv: &mut Mpv,
+    display: Arc<glutin::display::Display>,
+    event_tx: mpsc::Sender<MpvThreadEvent>,
+) -> Result<RenderContext> {
+    let mut render_context = RenderParams::opengl(display).create(mpv)?;
+    render_context.set_update_callback(move || {
+        event_tx.send(MpvThreadEvent::Redraw).ok();
+    });
+    Ok(render_context)
+}
+
+/// How long `wait_for_handles` keeps retrying before giving up.
+const WINDOW_HANDLE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Gets the raw handles of `window`. Right after startup the native window
+/// may not be realized yet on some platforms, so this retries with a
+/// doubling backoff until the handles are valid or the timeout is up.
+fn wait_for_handles(window: &tauri::WebviewWindow) -> Result<(RawWindowHandle, RawDisplayHandle)> {
+    let deadline = Instant::now() + WINDOW_HANDLE_TIMEOUT;
+    let mut backoff = Duration::from_millis(10);
+    loop {
+        let handles = window
+            .window_handle()
+            .and_then(|w| Ok((w.as_raw(), window.display_handle()?.as_raw())));
+        match handles {
+            Ok(handles) => return Ok(handles),
+            Err(e) if Instant::now() >= deadline => {
+                return Err(Error::Gl(format!("window handle never became valid: {e}")))
+            }
+            Err(_) => {
+                thread::sleep(backoff);
+                backoff = (backoff * 2).min(Duration::from_millis(500));
+            }
+        }
+    }
+}
+
+/// Sets up GL and mpv for `window`, then runs the render loop on the calling
+/// thread until the player shuts down.
+fn run_player(
+    window: tauri::WebviewWindow,
+    player_config: PlayerConfig,
+    event_tx: mpsc::Sender<MpvThreadEvent>,
+    event_rx: mpsc::Receiver<MpvThreadEvent>,
+) -> Result<()> {
+    let (raw_window_handle, raw_display_handle) = wait_for_handles(&window)?;
+
+    let display = Arc::new(unsafe {
+        #[cfg(windows)]
+        let preference = match player_config.gpu_context.as_deref() {
+            Some("egl" | "angle") => DisplayApiPreference::Egl,
+            Some("wgl") => DisplayApiPreference::Wgl(Some(raw_window_handle)),
+            _ => DisplayApiPreference::WglThenEgl(Some(raw_window_handle)),
+        };
+
+        #[cfg(all(unix, not(target_os = "macos")))]
+        let preference = {
+            match raw_display_handle {
+                raw_window_handle::RawDisplayHandle::Wayland(_) => DisplayApiPreference::Egl,
+                raw_window_handle::RawDisplayHandle::Xlib(_)
+                | raw_window_handle::RawDisplayHandle::Xcb(_) => {
+                    match player_config.gpu_context.as_deref() {
+                        Some("egl") => DisplayApiPreference::Egl,
+                        Some("glx") => DisplayApiPreference::Glx(Box::new(
+                            winit::platform::x11::register_xlib_error_hook,
+                        )),
+                        _ => DisplayApiPreference::GlxThenEgl(Box::new(
+                            winit::platform::x11::register_xlib_error_hook,
+                        )),
+                    }
+                }
+                _ => DisplayApiPreference::Egl,
+            }
+        };
+
+        #[cfg(target_os = "macos")]
+        let preference = DisplayApiPreference::Cgl;
+
+        glutin::display::Display::new(raw_display_handle, preference)?
+    });
+
+    let surface_attributes: glutin::surface::SurfaceAttributes<WindowSurface> = window
+        .build_surface_attributes(Default::default())
+        .map_err(|e| Error::Gl(e.to_string()))?;
+    let template = glutin::config::ConfigTemplateBuilder::new()
+        .compatible_with_native_window(raw_window_handle);
+
+    let config = unsafe { display.find_configs(template.build())?.next() }
+        .ok_or_else(|| Error::Gl("no suitable config found".into()))?;
+
+    let surface = unsafe { display.create_window_surface(&config, &surface_attributes)? };
+
+    let context_attributes =
+        glutin::context::ContextAttributesBuilder::new().build(Some(raw_window_handle));
+
+    let context = unsafe { display.create_context(&config, &context_attributes)? };
+    let current_context = context.make_current(&surface)?;
+
+    let gl =
+        unsafe { glow::Context::from_loader_function_cstr(|name| display.get_proc_address(name)) };
+
+    let mut mpv = Mpv::with_initializer(|init| {
+        init.set_option("vo", "libmpv")?;
+        init.set_option("hwdec", "auto-safe")?;
+        if let Some(ao) = &player_config.ao {
+            init.set_option("ao", ao.as_str())?;
+        }
+        let dirs = [
+            ("config-dir", &player_config.config_dir),
+            ("cache-dir", &player_config.cache_dir),
+            ("watch-later-dir", &player_config.watch_later_dir),
+        ];
+        for (name, dir) in dirs {
+            if let Some(dir) = dir.as_deref().and_then(|dir| prepare_dir(name, dir)) {
+                init.set_option(name, dir.as_str())?;
+            }
+        }
+        Ok(())
+    })?;
+
+    // Without a render context mpv can't bring up its video output, but it
+    // still plays audio, so carry on without video until `retry_render`.
+    let render_context = match create_render_context(&mut mpv, display, event_tx.clone()) {
+        Ok(render_context) => Some(render_context),
+        Err(e) => {
+            println!("Failed to create render context: {}", e);
+            let report = ErrorReport::new("Failed to create render context", &e);
+            window.emit("mpv-render-unavailable", report).ok();
+            None
+        }
+    };
+
+    let player_tx = event_tx.clone();
+    let resize_tx = event_tx.clone();
+    let close_tx = event_tx.clone();
+
+    mpv.set_wakeup_callback(move || {
+        event_tx.send(MpvThreadEvent::MpvEvents).ok();
+    });
+
+    let close_window = window.clone();
+    window.on_window_event(move |event| match event {
+        // Moving to a monitor with another scale factor changes the physical
+        // size without always sending `Resized` as well.
+        tauri::WindowEvent::Resized(_) | tauri::WindowEvent::ScaleFactorChanged { .. } => {
+            resize_tx.send(MpvThreadEvent::Resize).ok();
+        }
+        tauri::WindowEvent::CloseRequested { api, .. } => {
+            // Let the render thread save state while the file is
+            // still loaded; it destroys the window when done.
+            api.prevent_close();
+            let sent = close_tx.send(MpvThreadEvent::Call(Box::new(|player| {
+                if let Err(e) = player.save_file_settings() {
+                    println!("Failed to save file settings: {}", e);
+                }
+                player.window.destroy().ok();
+            })));
+            if sent.is_err() {
+                close_window.destroy().ok();
+            }
+        }
+        _ => {}
+    });
+
+    let video_path =
+        "https://commondatastorage.googleapis.com/gtv-videos-bucket/sample/BigBuckBunny.mp4";
+    mpv.command("loadfile", &[video_path, "replace"])?;
+
+    let mut player = Player::new(
+        window,
+        mpv,
+        render_context,
+        surface,
+        current_context,
+        gl,
+        player_tx,
+    );
+    player.requested_gpu = player_config.gpu;
+    player.render_to_texture = player_config.render_to_texture;
+    player.run(event_rx);
+    Ok(())
+}
+
+/// Asks the OS for the highest priority it grants this thread without special
+/// setup. On Linux that means a lower niceness, which needs `CAP_SYS_NICE` or
+/// an `RLIMIT_NICE` allowance; on Windows it is `THREAD_PRIORITY_HIGHEST` and
+/// on macOS the top of the normal priority band. If the OS refuses, the thread
+/// keeps its default priority.
+fn raise_thread_priority() {
+    if let Err(e) = thread_priority::set_current_thread_priority(ThreadPriority::Max) {
+        println!("Keeping default render thread priority: {:?}", e);
+    }
+}
+
+/// Asks for the GPU named by the `gpu` startup option before any GL is set
+/// up. On Linux this goes through Mesa's `DRI_PRIME`, which Mesa ignores with
+/// a warning (and picks the default GPU) if nothing matches.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn apply_gpu_preference(preference: &str) {
+    if std::env::var_os("DRI_PRIME").is_some() {
+        println!("DRI_PRIME is already set, ignoring the gpu option");
+        return;
+    }
+    let value = match preference {
+        "high-performance" => "1",
+        "low-power" => "0",
+        adapter => adapter,
+    };
+    std::env::set_var("DRI_PRIME", value);
+}
+
+/// On Windows the preference is the per-app entry the Settings app writes,
+/// which drivers only read at process start, so it applies from the next
+/// launch on.
+#[cfg(windows)]
+fn apply_gpu_preference(preference: &str) {
+    let value = match preference {
+        "high-performance" => "GpuPreference=2;",
+        "low-power" => "GpuPreference=1;",
+        _ => {
+            println!("Unknown gpu option {}, using the default GPU", preference);
+            return;
+        }
+    };
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) => {
+            println!("Failed to set GPU preference: {}", e);
+            return;
+        }
+    };
+    let result = winreg::RegKey::predef(winreg::enums::HKEY_CURRENT_USER)
+        .create_subkey(r"Software\Microsoft\DirectX\UserGpuPreferences")
+        .and_then(|(key, _)| key.set_value(exe.as_os_str(), &value));
+    if let Err(e) = result {
+        println!("Failed to set GPU preference: {}", e);
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn apply_gpu_preference(_preference: &str) {
+    println!("The gpu option isn't supported on macOS");
 }
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
@@ -74,151 +344,179 @@ pub fn run() {
         .setup(|app| {
             let window = app.get_webview_window("main").unwrap();
 
-            thread::spawn(move || {
-                let raw_window_handle = window.window_handle().unwrap().as_raw();
-                let raw_display_handle = window.display_handle().unwrap().as_raw();
-
-                let display = Arc::new(unsafe {
-                    #[cfg(windows)]
-                    let preference = DisplayApiPreference::WglThenEgl(Some(raw_window_handle));
-
-                    #[cfg(all(unix, not(target_os = "macos")))]
-                    let preference = {
-                        match raw_display_handle {
-                            raw_window_handle::RawDisplayHandle::Wayland(_) => DisplayApiPreference::Egl,
-                            raw_window_handle::RawDisplayHandle::Xlib(_)
-                            | raw_window_handle::RawDisplayHandle::Xcb(_) => DisplayApiPreference::GlxThenEgl(
-                                Box::new(winit::platform::x11::register_xlib_error_hook),
-                            ),
-                            _ => DisplayApiPreference::Egl,
-                        }
-                    };
-
-                    #[cfg(target_os = "macos")]
-                    let preference = DisplayApiPreference::Cgl;
-
-                    glutin::display::Display::new(raw_display_handle, preference)
-                        .expect("Failed to create glutin display")
-                });
-
-                let surface_attributes: glutin::surface::SurfaceAttributes<WindowSurface> =
-                    window.build_surface_attributes(Default::default()).unwrap();
-                let template = glutin::config::ConfigTemplateBuilder::new()
-                    .compatible_with_native_window(raw_window_handle);
-
-                let config = unsafe {
-                    display
-                        .find_configs(template.build())
-                        .unwrap()
-                        .next()
-                        .expect("No suitable config found")
-                };
-
-                let surface = unsafe {
-                    display
-                        .create_window_surface(&config, &surface_attributes)
-                        .expect("Failed to create window surface")
-                };
-
-                let context_attributes =
-                    glutin::context::ContextAttributesBuilder::new().build(Some(raw_window_handle));
-
-                let context = unsafe {
-                    display
-                        .create_context(&config, &context_attributes)
-                        .expect("Failed to create context")
-                };
-
-                let current_context = context
-                    .make_current(&surface)
-                    .expect("Failed to make context current");
-
-                let mut mpv = Mpv::with_initializer(|init| {
-                    init.set_option("vo", "libmpv")?;
-                    init.set_option("hwdec", "auto-safe")?;
-                    Ok(())
-                })
-                .expect("Failed to create mpv instance with initializer");
-
-                let mut render_context = RenderContext::new(
-                    unsafe { mpv.ctx.as_mut() },
-                    vec![
-                        RenderParam::ApiType(RenderParamApiType::OpenGl),
-                        RenderParam::InitParams(OpenGLInitParams {
-                            get_proc_address,
-                            ctx: display.clone(),
-                        }),
-                    ],
-                )
-                .expect("Failed creating render context");
-
-                let (event_tx, event_rx) = mpsc::channel::<MpvThreadEvent>();
-
-                let redraw_tx = event_tx.clone();
-                let resize_tx = event_tx.clone();
-
-                render_context.set_update_callback(move || {
-                    redraw_tx.send(MpvThreadEvent::Redraw).ok();
-                });
-
-                mpv.set_wakeup_callback(move || {
-                    event_tx.send(MpvThreadEvent::MpvEvents).ok();
-                });
-
-                window.on_window_event(move |event| match event {
-                    tauri::WindowEvent::Resized(_) => {
-                        resize_tx.send(MpvThreadEvent::Redraw).ok();
+            let player_config = match app.path().app_config_dir() {
+                Ok(dir) => PlayerConfig::load(&dir.join("player.json")),
+                Err(_) => PlayerConfig::default(),
+            };
+
+            // Before the render thread exists, since it changes the environment.
+            if let Some(gpu) = &player_config.gpu {
+                apply_gpu_preference(gpu);
+            }
+
+            let (event_tx, event_rx) = mpsc::channel::<MpvThreadEvent>();
+            app.manage(PlayerHandle::new(event_tx.clone()));
+
+            thread::Builder::new()
+                .name("mpv-render".into())
+                .spawn(move || {
+                    if player_config.raise_render_thread_priority {
+                        raise_thread_priority();
                     }
-                    _ => {}
-                });
-
-                let video_path = "https://commondatastorage.googleapis.com/gtv-videos-bucket/sample/BigBuckBunny.mp4";
-                mpv.command("loadfile", &[video_path, "replace"]).unwrap();
-
-                for event in event_rx {
-                    match event {
-                        MpvThreadEvent::Redraw => {
-                            let size = window.inner_size().unwrap();
-                            // println!("Redrawing frame at size: {}x{}", size.width, size.height);
-
-                            render_context
-                                .render::<Arc<glutin::display::Display>>(
-                                    0,
-                                    size.width as _,
-                                    size.height as _,
-                                    true,
-                                )
-                                .expect("Failed to draw video frame");
-
-                            surface
-                                .swap_buffers(&current_context)
-                                .expect("Failed to swap buffers");
-                        }
-                        MpvThreadEvent::MpvEvents => {
-                            while let Some(mpv_event) = mpv.wait_event(0.0) {
-                                match mpv_event {
-                                    Ok(Event::EndFile(_)) => {
-                                        println!("End of file detected. Exiting render thread.");
-                                        return;
-                                    }
-                                    Ok(e) => {
-                                        println!("Received MPV Event: {:?}", e);
-                                    }
-                                    Err(e) => {
-                                        println!("MPV event error: {}", e);
-                                        return;
-                                    }
-                                }
-                            }
-                        }
+                    let init_window = window.clone();
+                    if let Err(e) = run_player(window, player_config, event_tx, event_rx) {
+                        println!("Failed to start player: {}", e);
+                        init_window.emit("mpv-init-error", e.to_string()).ok();
                     }
-                }
-            });
+                })?;
 
             Ok(())
         })
         .plugin(tauri_plugin_opener::init())
-        .invoke_handler(tauri::generate_handler![greet])
+        .invoke_handler(tauri::generate_handler![
+            greet,
+            commands::audio::set_replaygain,
+            commands::audio::get_ao_current,
+            commands::audio::set_audio_exclusive,
+            commands::audio::add_audio,
+            commands::audio::generate_waveform,
+            commands::audio::set_av_delay_compensation,
+            commands::audio::switch_audio_device_with_fade,
+            commands::audio::set_visualizer,
+            commands::audio::set_on_device_lost,
+            commands::cache::set_cache_on_disk,
+            commands::cache::flush_cache,
+            commands::cache::set_prebuffer,
+            commands::cache::set_decode_queue,
+            commands::cache::set_low_latency,
+            commands::cache::set_cache_pause,
+            commands::video::retry_render,
+            commands::video::grab_frame_rgba,
+            commands::video::get_frame_luminance,
+            commands::video::set_canvas_mode,
+            commands::video::get_canvas_frame,
+            commands::video::set_block_render,
+            commands::video::render_once,
+            commands::video::has_rendered_frame,
+            commands::video::set_frame_pts_events,
+            commands::video::set_redraw_min_interval_ms,
+            commands::video::export_frame_with_subs,
+            commands::video::dump_frames,
+            commands::video::generate_chapter_thumbnails,
+            commands::video::benchmark_decode,
+            commands::video::set_hwdec,
+            commands::video::set_hwdec_device,
+            commands::video::set_3d_mode,
+            commands::video::set_color_range,
+            commands::video::set_video_enabled,
+            commands::video::set_force_window,
+            commands::video::set_framedrop,
+            commands::video::set_letterbox_color,
+            commands::video::set_clear_behavior,
+            commands::video::get_gpu_context,
+            commands::video::get_gl_caps,
+            commands::video::get_active_gpu,
+            commands::video::get_render_texture,
+            commands::video::set_max_resolution,
+            commands::video::get_display_fps,
+            commands::video::set_override_display_fps,
+            commands::window::set_cursor_autohide,
+            commands::window::mirror_to_window,
+            commands::window::set_borderless,
+            commands::window::set_release_gl_on_minimize,
+            commands::diagnostics::get_resource_usage,
+            commands::diagnostics::set_resource_usage_interval,
+            commands::diagnostics::get_av_sync,
+            commands::diagnostics::set_av_sync_interval,
+            commands::diagnostics::get_event_latency_stats,
+            commands::diagnostics::set_event_latency_interval,
+            commands::diagnostics::dump_all_properties,
+            commands::diagnostics::apply_property_dump,
+            commands::diagnostics::get_dirs,
+            commands::filters::add_vf,
+            commands::filters::remove_vf,
+            commands::filters::add_af,
+            commands::filters::remove_af,
+            commands::osd::toggle_stats_overlay,
+            commands::osd::set_osc,
+            commands::osd::set_osd_bar,
+            commands::osd::show_progress,
+            commands::osd::send_pointer,
+            commands::playback::get_status,
+            commands::playback::load_file,
+            commands::playback::set_autoplay_default,
+            commands::playback::set_resume_prompt,
+            commands::playback::load_bytes,
+            commands::playback::flush_commands,
+            commands::playback::wait_until_loaded,
+            commands::playback::preview_seek,
+            commands::playback::commit_seek,
+            commands::playback::get_seekable_ranges,
+            commands::playback::get_buffered_ranges,
+            commands::playback::seek,
+            commands::playback::seek_step,
+            commands::playback::set_seek_step,
+            commands::playback::get_frame_number,
+            commands::playback::seek_to_frame,
+            commands::playback::restart,
+            commands::playback::set_demuxer_readahead,
+            commands::playback::set_probe_options,
+            commands::playback::set_hr_seek,
+            commands::playback::save_file_settings,
+            commands::playback::load_file_settings,
+            commands::playback::set_speed,
+            commands::playback::set_speed_ramp,
+            commands::playback::loop_segment,
+            commands::playback::reset_player,
+            commands::playlist::export_playlist_state,
+            commands::playlist::import_playlist_state,
+            commands::playlist::get_playlist_pos,
+            commands::playlist::set_playlist_pos,
+            commands::playlist::peek_next,
+            commands::playlist::peek_prev,
+            commands::playlist::confirm_peek,
+            commands::playlist::cancel_peek,
+            commands::playlist::set_prefetch_next,
+            commands::subtitle::set_ass_override,
+            commands::subtitle::set_sub_font,
+            commands::subtitle::set_sub_scale_with_window,
+            commands::subtitle::set_sub_scale,
+            commands::subtitle::set_sub_use_margins,
+            commands::subtitle::set_sub_ass_force_margins,
+            commands::subtitle::set_sub_backend,
+            commands::subtitle::set_sub_preload,
+            commands::subtitle::nudge_sub_delay,
+            commands::subtitle::set_sub_encoding,
+            commands::subtitle::get_attachments,
+            commands::subtitle::set_embedded_fonts,
+            commands::subtitle::add_subtitle,
+            commands::tracks::set_lang_prefs,
+            commands::tracks::get_editions,
+            commands::tracks::set_edition,
+            commands::tracks::get_track_counts,
+            commands::tracks::set_autoload_sidecars,
+            commands::tracks::can_decode,
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failed_mpv_init_becomes_an_error() {
+        let result = Mpv::with_initializer(|init| init.set_option("not-an-mpv-option", "yes"));
+        let err = Error::from(result.err().expect("mpv accepted an unknown option"));
+        assert_eq!(err.to_string(), "mpv: option not found");
+    }
+
+    #[test]
+    fn mpv_handle_reaches_the_initialized_core() {
+        let mut mpv = Mpv::with_initializer(|init| init.set_option("vo", "null")).unwrap();
+        let handle: *mut libmpv2_sys::mpv_handle = mpv_handle(&mut mpv);
+        // The main handle is the first client mpv creates.
+        assert_eq!(unsafe { libmpv2_sys::mpv_client_id(handle) }, 1);
+    }
+}
@@ -0,0 +1,70 @@
+use glutin::config::GetGlConfig;
+use glutin::context::PossiblyCurrentContext;
+use glutin::display::GetGlDisplay;
+use glutin::prelude::*;
+use glutin::surface::{Surface, SwapInterval, WindowSurface};
+use libmpv2::render::RenderContext;
+use std::sync::Arc;
+use tauri::WebviewWindow;
+
+use crate::player::Player;
+use crate::{Error, GlWindow, Result};
+
+/// A second window showing the same video as the main one.
+///
+/// A GL context can draw to any surface created with its config, so the
+/// mirror gets its own window surface while sharing the player's context, and
+/// with it mpv's render context. Each frame is rendered once per window; mpv
+/// redraws the frame it already has for the second call.
+///
+/// Both windows have to live on the same display connection and GPU. Tauri's
+/// windows always share a connection, but a monitor driven by another GPU may
+/// refuse the config, in which case setup fails and a second mpv instance is
+/// the only way to drive it.
+pub struct Mirror {
+    pub window: WebviewWindow,
+    surface: Surface<WindowSurface>,
+}
+
+impl Mirror {
+    pub fn new(player: &Player, window: WebviewWindow) -> Result<Self> {
+        let attributes = window
+            .build_surface_attributes(Default::default())
+            .map_err(|e| Error::Gl(e.to_string()))?;
+        let display = player.context.display();
+        let config = player.context.config();
+        let surface = unsafe { display.create_window_surface(&config, &attributes)? };
+
+        // The main window's swap already waits for vsync; waiting a second
+        // time here would halve the frame rate.
+        player.context.make_current(&surface)?;
+        let interval = surface.set_swap_interval(&player.context, SwapInterval::DontWait);
+        player.context.make_current(&player.surface)?;
+        interval?;
+
+        Ok(Self { window, surface })
+    }
+
+    /// Renders the current frame into the mirror window. Leaves the mirror's
+    /// surface current, so the caller has to switch back afterwards.
+    pub fn draw(
+        &self,
+        render_context: &RenderContext,
+        context: &PossiblyCurrentContext,
+    ) -> Result<()> {
+        // Fails once the window is gone; the close handler drops us shortly.
+        let Ok(size) = self.window.inner_size() else {
+            return Ok(());
+        };
+
+        context.make_current(&self.surface)?;
+        render_context.render::<Arc<glutin::display::Display>>(
+            0,
+            size.width as _,
+            size.height as _,
+            true,
+        )?;
+        self.surface.swap_buffers(context)?;
+        Ok(())
+    }
+}
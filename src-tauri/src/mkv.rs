@@ -0,0 +1,495 @@
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::Result;
+
+// Matroska element ids, marker bits included.
+const EBML: u32 = 0x1A45_DFA3;
+const SEGMENT: u32 = 0x1853_8067;
+const SEEK_HEAD: u32 = 0x114D_9B74;
+const SEEK: u32 = 0x4DBB;
+const SEEK_ID: u32 = 0x53AB;
+const SEEK_POSITION: u32 = 0x53AC;
+const ATTACHMENTS: u32 = 0x1941_A469;
+const ATTACHED_FILE: u32 = 0x61A7;
+const FILE_NAME: u32 = 0x466E;
+const FILE_MIME_TYPE: u32 = 0x4660;
+const FILE_DATA: u32 = 0x465C;
+
+/// Top-level elements looked at before giving up on finding attachments.
+const MAX_TOP_LEVEL_ELEMENTS: usize = 4096;
+
+/// Longest name or MIME type read, to bound damaged files.
+const MAX_STRING_LEN: u64 = 4096;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attachment {
+    pub name: String,
+    pub mime_type: String,
+    pub size: u64,
+}
+
+impl Attachment {
+    /// Whether libass would load this as a font, going by the same MIME types
+    /// and extensions mpv checks.
+    pub fn is_font(&self) -> bool {
+        let mime = self.mime_type.to_ascii_lowercase();
+        let name = self.name.to_ascii_lowercase();
+        mime.starts_with("font/")
+            || matches!(
+                mime.as_str(),
+                "application/x-truetype-font"
+                    | "application/vnd.ms-opentype"
+                    | "application/x-font-ttf"
+                    | "application/x-font"
+                    | "application/font-sfnt"
+            )
+            || [".ttf", ".ttc", ".otf"]
+                .iter()
+                .any(|ext| name.ends_with(ext))
+    }
+}
+
+struct Reader<R> {
+    inner: R,
+}
+
+impl<R: Read + Seek> Reader<R> {
+    /// Reads a variable-length integer. Ids keep their length marker, sizes
+    /// drop it. `None` is an unknown size (all value bits set).
+    fn vint(&mut self, keep_marker: bool) -> Result<Option<u64>> {
+        let mut first = [0];
+        self.inner.read_exact(&mut first)?;
+        let len = first[0].leading_zeros() as usize + 1;
+        if len > 8 {
+            return Err(invalid("bad element header"));
+        }
+        let mask = 0xFF_u64 >> len;
+        let mut value = if keep_marker {
+            first[0] as u64
+        } else {
+            first[0] as u64 & mask
+        };
+        let mut all_ones = value == mask;
+        for _ in 1..len {
+            let mut byte = [0];
+            self.inner.read_exact(&mut byte)?;
+            value = value << 8 | byte[0] as u64;
+            all_ones &= byte[0] == 0xFF;
+        }
+        Ok((keep_marker || !all_ones).then_some(value))
+    }
+
+    /// Reads an element header as `(id, size)`.
+    fn header(&mut self) -> Result<(u32, Option<u64>)> {
+        let id = self.vint(true)?.unwrap_or_default() as u32;
+        Ok((id, self.vint(false)?))
+    }
+
+    fn position(&mut self) -> Result<u64> {
+        Ok(self.inner.stream_position()?)
+    }
+
+    /// Where an element of `size` starting here ends.
+    fn end_of(&mut self, size: u64) -> Result<u64> {
+        self.position()?
+            .checked_add(size)
+            .ok_or_else(|| invalid("oversized element"))
+    }
+
+    fn skip(&mut self, size: u64) -> Result<()> {
+        let end = self.end_of(size)?;
+        self.inner.seek(SeekFrom::Start(end))?;
+        Ok(())
+    }
+
+    fn bytes(&mut self, size: u64) -> Result<Vec<u8>> {
+        if size > MAX_STRING_LEN {
+            return Err(invalid("oversized element"));
+        }
+        let mut bytes = vec![0; size as usize];
+        self.inner.read_exact(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn string(&mut self, size: u64) -> Result<String> {
+        let bytes = self.bytes(size)?;
+        let text = String::from_utf8_lossy(&bytes);
+        Ok(text.trim_end_matches('\0').to_owned())
+    }
+
+    fn uint(&mut self, size: u64) -> Result<u64> {
+        if size > 8 {
+            return Err(invalid("oversized integer"));
+        }
+        Ok(self
+            .bytes(size)?
+            .iter()
+            .fold(0, |value, &byte| value << 8 | byte as u64))
+    }
+
+    /// Runs `child` for every element inside the one of `size` just read.
+    fn children(
+        &mut self,
+        size: u64,
+        mut child: impl FnMut(&mut Self, u32, u64) -> Result<()>,
+    ) -> Result<()> {
+        let end = self.end_of(size)?;
+        while self.position()? < end {
+            let (id, size) = self.header()?;
+            let size = size.ok_or_else(|| invalid("unknown size inside a master element"))?;
+            let child_end = self.end_of(size)?;
+            if child_end > end {
+                return Err(invalid("element larger than its parent"));
+            }
+            child(self, id, size)?;
+            self.inner.seek(SeekFrom::Start(child_end))?;
+        }
+        Ok(())
+    }
+
+    /// Where `SeekHead` says `Attachments` is, relative to the segment data.
+    fn seek_head_attachments(&mut self, size: u64) -> Result<Option<u64>> {
+        let mut found = None;
+        self.children(size, |reader, id, size| {
+            if id != SEEK {
+                return Ok(());
+            }
+            let (mut seek_id, mut position) = (None, None);
+            reader.children(size, |reader, id, size| {
+                match id {
+                    SEEK_ID => seek_id = Some(reader.uint(size)?),
+                    SEEK_POSITION => position = Some(reader.uint(size)?),
+                    _ => {}
+                }
+                Ok(())
+            })?;
+            if seek_id == Some(ATTACHMENTS as u64) {
+                found = position;
+            }
+            Ok(())
+        })?;
+        Ok(found)
+    }
+
+    fn attachments(&mut self, size: u64) -> Result<Vec<Attachment>> {
+        let mut attachments = Vec::new();
+        self.children(size, |reader, id, size| {
+            if id != ATTACHED_FILE {
+                return Ok(());
+            }
+            let mut attachment = Attachment {
+                name: String::new(),
+                mime_type: String::new(),
+                size: 0,
+            };
+            reader.children(size, |reader, id, size| {
+                match id {
+                    FILE_NAME => attachment.name = reader.string(size)?,
+                    FILE_MIME_TYPE => attachment.mime_type = reader.string(size)?,
+                    FILE_DATA => attachment.size = size,
+                    _ => {}
+                }
+                Ok(())
+            })?;
+            attachments.push(attachment);
+            Ok(())
+        })?;
+        Ok(attachments)
+    }
+}
+
+fn invalid(what: &str) -> crate::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Matroska: {what}")).into()
+}
+
+/// Lists the files attached to the Matroska/WebM file at `path` (fonts,
+/// cover art, ...), reading only the element headers and the attachment
+/// names, not their data. Files of other formats have none.
+///
+/// libmpv has no property for attachments, so this reads the container
+/// itself: `Attachments` is found through the `SeekHead` or, failing that,
+/// by walking the top-level elements from the start.
+pub fn attachments(path: &Path) -> Result<Vec<Attachment>> {
+    read_attachments(BufReader::new(File::open(path)?))
+}
+
+fn read_attachments(inner: impl Read + Seek) -> Result<Vec<Attachment>> {
+    let mut reader = Reader { inner };
+    match reader.header() {
+        Ok((EBML, Some(size))) => reader.skip(size)?,
+        _ => return Ok(Vec::new()),
+    }
+    let (id, segment_size) = reader.header()?;
+    if id != SEGMENT {
+        return Ok(Vec::new());
+    }
+    let segment_start = reader.position()?;
+    let segment_end = segment_size.map(|size| segment_start.saturating_add(size));
+
+    for _ in 0..MAX_TOP_LEVEL_ELEMENTS {
+        if segment_end.is_some_and(|end| reader.position().is_ok_and(|pos| pos >= end)) {
+            break;
+        }
+        let Ok((id, size)) = reader.header() else {
+            // End of file.
+            break;
+        };
+        // Only clusters of a live recording have unknown sizes; nothing
+        // past one can be reached without parsing every block.
+        let Some(size) = size else {
+            break;
+        };
+        let end = reader.end_of(size)?;
+        match id {
+            ATTACHMENTS => return reader.attachments(size),
+            SEEK_HEAD => {
+                if let Some(position) = reader.seek_head_attachments(size)? {
+                    let position = segment_start
+                        .checked_add(position)
+                        .ok_or_else(|| invalid("bad seek position"))?;
+                    reader.inner.seek(SeekFrom::Start(position))?;
+                    if let (ATTACHMENTS, Some(size)) = reader.header()? {
+                        return reader.attachments(size);
+                    }
+                }
+            }
+            _ => {}
+        }
+        reader.inner.seek(SeekFrom::Start(end))?;
+    }
+    Ok(Vec::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const VOID: u32 = 0xEC;
+    const CLUSTER: u32 = 0x1F43_B675;
+    /// An 8-byte size with all value bits set.
+    const UNKNOWN_SIZE: [u8; 8] = [0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF];
+
+    fn reader(bytes: &[u8]) -> Reader<Cursor<Vec<u8>>> {
+        Reader {
+            inner: Cursor::new(bytes.to_vec()),
+        }
+    }
+
+    fn id_bytes(id: u32) -> Vec<u8> {
+        let bytes = id.to_be_bytes();
+        let skip = bytes.iter().take_while(|&&byte| byte == 0).count();
+        bytes[skip..].to_vec()
+    }
+
+    /// An element with an 8-byte size, so offsets are easy to work out.
+    fn element(id: u32, body: &[u8]) -> Vec<u8> {
+        let mut bytes = id_bytes(id);
+        bytes.push(0x01);
+        bytes.extend_from_slice(&(body.len() as u64).to_be_bytes()[1..]);
+        bytes.extend_from_slice(body);
+        bytes
+    }
+
+    fn attached_file(name: &str, mime_type: &str, data: &[u8]) -> Vec<u8> {
+        element(
+            ATTACHED_FILE,
+            &[
+                element(FILE_NAME, name.as_bytes()),
+                element(FILE_MIME_TYPE, mime_type.as_bytes()),
+                element(FILE_DATA, data),
+            ]
+            .concat(),
+        )
+    }
+
+    fn seek(target: u32, position: u64) -> Vec<u8> {
+        element(
+            SEEK,
+            &[
+                element(SEEK_ID, &id_bytes(target)),
+                element(SEEK_POSITION, &position.to_be_bytes()),
+            ]
+            .concat(),
+        )
+    }
+
+    fn seek_head(target: u32, position: u64) -> Vec<u8> {
+        element(SEEK_HEAD, &seek(target, position))
+    }
+
+    /// An EBML header and an unknown-size segment holding `children`.
+    fn file(children: &[Vec<u8>]) -> Vec<u8> {
+        let mut bytes = element(EBML, &[]);
+        bytes.extend(id_bytes(SEGMENT));
+        bytes.extend(UNKNOWN_SIZE);
+        bytes.extend(children.concat());
+        bytes
+    }
+
+    fn names(attachments: &[Attachment]) -> Vec<&str> {
+        attachments.iter().map(|a| a.name.as_str()).collect()
+    }
+
+    #[test]
+    fn vint_reads_every_length() {
+        for len in 1..=8 {
+            let mut bytes = vec![0; len];
+            bytes[0] = 0x80 >> (len - 1);
+            bytes[len - 1] |= 0x05;
+            assert_eq!(reader(&bytes).vint(false).unwrap(), Some(5), "length {len}");
+            let with_marker = bytes
+                .iter()
+                .fold(0, |value, &byte| value << 8 | byte as u64);
+            assert_eq!(reader(&bytes).vint(true).unwrap(), Some(with_marker));
+        }
+    }
+
+    #[test]
+    fn vint_rejects_a_leading_zero_byte() {
+        assert!(reader(&[0x00, 0x01]).vint(false).is_err());
+    }
+
+    #[test]
+    fn vint_fails_when_truncated() {
+        assert!(reader(&[]).vint(false).is_err());
+        assert!(reader(&[0x20, 0x00]).vint(false).is_err());
+    }
+
+    #[test]
+    fn vint_reads_unknown_sizes() {
+        assert_eq!(reader(&[0xFF]).vint(false).unwrap(), None);
+        assert_eq!(reader(&UNKNOWN_SIZE).vint(false).unwrap(), None);
+        // Ids keep all their bits.
+        assert_eq!(reader(&[0xFF]).vint(true).unwrap(), Some(0xFF));
+    }
+
+    #[test]
+    fn header_reads_id_and_size() {
+        assert_eq!(
+            reader(&[0x4D, 0xBB, 0x84]).header().unwrap(),
+            (SEEK, Some(4))
+        );
+        assert_eq!(reader(&[0xEC, 0xFF]).header().unwrap(), (VOID, None));
+    }
+
+    #[test]
+    fn children_visits_each_element_once() {
+        let body = [element(VOID, b"abc"), element(FILE_NAME, b"x")].concat();
+        let mut reader = reader(&body);
+        let mut seen = Vec::new();
+        reader
+            .children(body.len() as u64, |_, id, size| {
+                // Leaving the body unread must not matter.
+                seen.push((id, size));
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(seen, [(VOID, 3), (FILE_NAME, 1)]);
+        assert_eq!(reader.position().unwrap(), body.len() as u64);
+    }
+
+    #[test]
+    fn children_rejects_bad_sizes() {
+        let unknown = [&id_bytes(VOID)[..], &[0xFF]].concat();
+        assert!(reader(&unknown).children(2, |_, _, _| Ok(())).is_err());
+
+        let too_big = element(VOID, b"abcd");
+        assert!(reader(&too_big).children(4, |_, _, _| Ok(())).is_err());
+
+        let truncated = &element(VOID, b"abcd")[..5];
+        assert!(reader(truncated).children(13, |_, _, _| Ok(())).is_err());
+
+        let mut overflowing = reader(&[0x80]);
+        overflowing.vint(false).unwrap();
+        assert!(overflowing.children(u64::MAX, |_, _, _| Ok(())).is_err());
+    }
+
+    #[test]
+    fn strings_and_ints_are_bounded() {
+        assert!(reader(&[]).string(MAX_STRING_LEN + 1).is_err());
+        assert!(reader(&[0; 9]).uint(9).is_err());
+        assert!(reader(b"ab").string(4).is_err());
+    }
+
+    #[test]
+    fn seek_head_finds_attachments() {
+        let body = [seek(CLUSTER, 7), element(VOID, &[]), seek(ATTACHMENTS, 42)].concat();
+        let found = reader(&body).seek_head_attachments(body.len() as u64);
+        assert_eq!(found.unwrap(), Some(42));
+
+        let body = seek(CLUSTER, 7);
+        let found = reader(&body).seek_head_attachments(body.len() as u64);
+        assert_eq!(found.unwrap(), None);
+    }
+
+    #[test]
+    fn attachments_reads_names_types_and_sizes() {
+        let body = [
+            attached_file("font.ttf", "font/ttf", &[0; 10]),
+            element(VOID, &[]),
+            attached_file("cover.jpg", "image/jpeg", &[0; 3]),
+        ]
+        .concat();
+        let attachments = reader(&body).attachments(body.len() as u64).unwrap();
+        assert_eq!(names(&attachments), ["font.ttf", "cover.jpg"]);
+        assert_eq!(attachments[0].mime_type, "font/ttf");
+        assert_eq!(attachments[0].size, 10);
+        assert!(attachments[0].is_font());
+        assert!(!attachments[1].is_font());
+    }
+
+    #[test]
+    fn file_attachments_through_seek_head() {
+        let attachments = element(ATTACHMENTS, &attached_file("a.otf", "", &[]));
+        let head_len = seek_head(ATTACHMENTS, 0).len();
+        let cluster = [id_bytes(CLUSTER), vec![0xFF]].concat();
+        // The unknown-size cluster stops a linear walk, so only the SeekHead
+        // can lead past it.
+        let position = (head_len + cluster.len()) as u64;
+        let bytes = file(&[seek_head(ATTACHMENTS, position), cluster, attachments]);
+        let found = read_attachments(Cursor::new(bytes)).unwrap();
+        assert_eq!(names(&found), ["a.otf"]);
+    }
+
+    #[test]
+    fn file_attachments_without_seek_head() {
+        let bytes = file(&[
+            seek_head(CLUSTER, 0),
+            element(VOID, &[0; 16]),
+            element(ATTACHMENTS, &attached_file("b.ttf", "", &[])),
+        ]);
+        let found = read_attachments(Cursor::new(bytes)).unwrap();
+        assert_eq!(names(&found), ["b.ttf"]);
+    }
+
+    #[test]
+    fn file_without_attachments() {
+        let bytes = file(&[element(VOID, &[0; 4])]);
+        assert!(read_attachments(Cursor::new(bytes)).unwrap().is_empty());
+        let not_matroska = b"RIFF\x00\x00\x00\x00WAVE".to_vec();
+        assert!(read_attachments(Cursor::new(not_matroska))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn file_with_bad_sizes_is_an_error() {
+        let mut truncated = file(&[element(ATTACHMENTS, &attached_file("c.ttf", "", &[]))]);
+        truncated.truncate(truncated.len() - 4);
+        assert!(read_attachments(Cursor::new(truncated)).is_err());
+
+        let bytes = file(&[seek_head(ATTACHMENTS, u64::MAX)]);
+        assert!(read_attachments(Cursor::new(bytes)).is_err());
+
+        let mut oversized = element(ATTACHMENTS, &element(FILE_NAME, b"x"));
+        // FileName claims to be larger than Attachments.
+        let len = oversized.len();
+        oversized[len - 2] = 0x7F;
+        assert!(read_attachments(Cursor::new(file(&[oversized]))).is_err());
+    }
+}
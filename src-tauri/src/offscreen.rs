@@ -0,0 +1,370 @@
+use glow::HasContext;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::player::Player;
+use crate::{Error, Result};
+
+/// How long `render_offscreen` waits for mpv to decode the first frame.
+const FIRST_FRAME_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A texture-backed framebuffer, RGBA8 unless made with `with_format`, that
+/// mpv can render into instead of the window's backbuffer.
+pub struct Framebuffer {
+    fbo: glow::Framebuffer,
+    texture: glow::Texture,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Framebuffer {
+    pub fn new(gl: &glow::Context, width: i32, height: i32) -> Result<Self> {
+        Self::with_format(gl, width, height, glow::RGBA8, glow::UNSIGNED_BYTE)
+    }
+
+    /// Returns the framebuffer in `slot`, replacing it with a new one first
+    /// if there is none yet or it has a different size.
+    pub fn ensure<'a>(
+        gl: &glow::Context,
+        slot: &'a mut Option<Self>,
+        width: i32,
+        height: i32,
+    ) -> Result<&'a Self> {
+        if slot
+            .as_ref()
+            .is_some_and(|target| target.width == width && target.height == height)
+        {
+            return Ok(slot.as_ref().unwrap());
+        }
+        if let Some(old) = slot.take() {
+            old.delete(gl);
+        }
+        Ok(slot.insert(Self::new(gl, width, height)?))
+    }
+
+    /// Like `new`, with a texture of `internal_format` (e.g. `RGBA16F`),
+    /// allocated as RGBA data of `data_type`. Fails if the driver can't
+    /// render to that format.
+    pub fn with_format(
+        gl: &glow::Context,
+        width: i32,
+        height: i32,
+        internal_format: u32,
+        data_type: u32,
+    ) -> Result<Self> {
+        unsafe {
+            let texture = gl.create_texture().map_err(Error::Gl)?;
+            gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+            gl.tex_image_2d(
+                glow::TEXTURE_2D,
+                0,
+                internal_format as i32,
+                width,
+                height,
+                0,
+                glow::RGBA,
+                data_type,
+                glow::PixelUnpackData::Slice(None),
+            );
+            gl.tex_parameter_i32(
+                glow::TEXTURE_2D,
+                glow::TEXTURE_MIN_FILTER,
+                glow::LINEAR as i32,
+            );
+            gl.tex_parameter_i32(
+                glow::TEXTURE_2D,
+                glow::TEXTURE_MAG_FILTER,
+                glow::LINEAR as i32,
+            );
+            gl.bind_texture(glow::TEXTURE_2D, None);
+
+            let fbo = match gl.create_framebuffer() {
+                Ok(fbo) => fbo,
+                Err(e) => {
+                    gl.delete_texture(texture);
+                    return Err(Error::Gl(e));
+                }
+            };
+            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(fbo));
+            gl.framebuffer_texture_2d(
+                glow::FRAMEBUFFER,
+                glow::COLOR_ATTACHMENT0,
+                glow::TEXTURE_2D,
+                Some(texture),
+                0,
+            );
+            let status = gl.check_framebuffer_status(glow::FRAMEBUFFER);
+            gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+
+            let framebuffer = Self {
+                fbo,
+                texture,
+                width,
+                height,
+            };
+            if status != glow::FRAMEBUFFER_COMPLETE {
+                framebuffer.delete(gl);
+                return Err(Error::Gl(format!("incomplete framebuffer ({status:#x})")));
+            }
+            Ok(framebuffer)
+        }
+    }
+
+    /// The GL name to hand to `RenderContext::render`.
+    pub fn id(&self) -> i32 {
+        self.fbo.0.get() as i32
+    }
+
+    /// The GL name of the color texture.
+    pub fn texture_id(&self) -> u32 {
+        self.texture.0.get()
+    }
+
+    /// Copies the framebuffer 1:1 into the bottom-left of the default one.
+    pub fn blit_to_default(&self, gl: &glow::Context) {
+        unsafe {
+            gl.bind_framebuffer(glow::READ_FRAMEBUFFER, Some(self.fbo));
+            gl.bind_framebuffer(glow::DRAW_FRAMEBUFFER, None);
+            gl.blit_framebuffer(
+                0,
+                0,
+                self.width,
+                self.height,
+                0,
+                0,
+                self.width,
+                self.height,
+                glow::COLOR_BUFFER_BIT,
+                glow::NEAREST,
+            );
+            gl.bind_framebuffer(glow::READ_FRAMEBUFFER, None);
+        }
+    }
+
+    /// Reads the whole framebuffer back as RGBA8, bottom row first (GL order).
+    pub fn read_rgba(&self, gl: &glow::Context) -> Vec<u8> {
+        let mut pixels = vec![0; self.width as usize * self.height as usize * 4];
+        unsafe {
+            gl.bind_framebuffer(glow::READ_FRAMEBUFFER, Some(self.fbo));
+            gl.pixel_store_i32(glow::PACK_ALIGNMENT, 1);
+            gl.read_pixels(
+                0,
+                0,
+                self.width,
+                self.height,
+                glow::RGBA,
+                glow::UNSIGNED_BYTE,
+                glow::PixelPackData::Slice(Some(&mut pixels)),
+            );
+            gl.bind_framebuffer(glow::READ_FRAMEBUFFER, None);
+        }
+        pixels
+    }
+
+    pub fn delete(self, gl: &glow::Context) {
+        unsafe {
+            gl.delete_framebuffer(self.fbo);
+            gl.delete_texture(self.texture);
+        }
+    }
+}
+
+/// Clears `target`, or the window's default framebuffer if `None`, to the
+/// straight-alpha RGBA `color`.
+pub fn clear(gl: &glow::Context, target: Option<&Framebuffer>, color: [f32; 4]) {
+    let [r, g, b, a] = color;
+    unsafe {
+        gl.bind_framebuffer(glow::FRAMEBUFFER, target.map(|target| target.fbo));
+        gl.clear_color(r, g, b, a);
+        gl.clear(glow::COLOR_BUFFER_BIT);
+        gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+    }
+}
+
+/// Renders the current frame at its display size into a temporary
+/// framebuffer and reads it back as straight-alpha RGBA8, top row first.
+///
+/// Like any `render()`, this pulls the next queued frame. The window is
+/// redrawn straight after, which draws that same frame again, so the grab
+/// doesn't make the display skip it.
+pub fn grab_frame_rgba(player: &mut Player) -> Result<(u32, u32, Vec<u8>)> {
+    let width = player.mpv.get_property::<i64>("dwidth")? as i32;
+    let height = player.mpv.get_property::<i64>("dheight")? as i32;
+
+    let render_context = player.render_context()?;
+    let target = Framebuffer::new(&player.gl, width, height)?;
+    // Unflipped, mpv writes the top row at y = 0, which is the row order
+    // glReadPixels returns first, so the readback needs no CPU-side flip.
+    let pixels = render_context
+        .render::<Arc<glutin::display::Display>>(target.id(), width, height, false)
+        .map(|()| target.read_rgba(&player.gl));
+    target.delete(&player.gl);
+    player.redraw();
+
+    let mut pixels = pixels?;
+    unpremultiply(&mut pixels);
+    Ok((width as u32, height as u32, pixels))
+}
+
+/// Width `frame_luminance` renders at; the height follows the aspect ratio.
+const LUMINANCE_WIDTH: i64 = 64;
+
+/// Renders the current frame downscaled to 64 pixels wide into a temporary
+/// framebuffer and averages its Rec. 709 luma, from 0 (black) to 1 (white).
+///
+/// mpv scales the frame on the GPU, so only a few kilobytes are read back
+/// whatever the video size. Hardware-decoded frames are turned into GL
+/// textures for any render, so this works the same with any `hwdec`. The
+/// render pulls the next queued frame, so like `grab_frame_rgba` the window
+/// is redrawn straight after and the display doesn't skip it.
+pub fn frame_luminance(player: &mut Player) -> Result<f64> {
+    let dwidth = player.mpv.get_property::<i64>("dwidth")?;
+    let dheight = player.mpv.get_property::<i64>("dheight")?;
+    if dwidth <= 0 || dheight <= 0 {
+        return Err(Error::Mpv("no video frame to measure".into()));
+    }
+    let width = LUMINANCE_WIDTH as i32;
+    let height = (LUMINANCE_WIDTH * dheight / dwidth).max(1) as i32;
+
+    let render_context = player.render_context()?;
+    let target = Framebuffer::new(&player.gl, width, height)?;
+    let pixels = render_context
+        .render::<Arc<glutin::display::Display>>(target.id(), width, height, false)
+        .map(|()| target.read_rgba(&player.gl));
+    target.delete(&player.gl);
+    player.redraw();
+
+    let pixels = pixels?;
+    let total: f64 = pixels
+        .chunks_exact(4)
+        .map(|px| 0.2126 * px[0] as f64 + 0.7152 * px[1] as f64 + 0.0722 * px[2] as f64)
+        .sum();
+    Ok(total / 255.0 / (width * height) as f64)
+}
+
+/// Renders the first frame of `path` at `width`x`height` without any window
+/// and returns it as straight-alpha RGBA8, top row first.
+///
+/// Everything is set up from scratch the way `run_player` does it, minus the
+/// window: an EGL display on the first device (`EGL_EXT_device_enumeration`,
+/// which Mesa's software rasterizer also offers, so CI machines without a
+/// display server or GPU work), a pbuffer surface to make the context current
+/// on, and a fresh mpv with its own render context. EGL only, so not on
+/// Windows or macOS.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn render_offscreen(path: &str, width: u32, height: u32) -> Result<Vec<u8>> {
+    use crate::render_params::RenderParams;
+    use glutin::api::egl;
+    use glutin::config::{ConfigSurfaceTypes, ConfigTemplateBuilder};
+    use glutin::context::{ContextAttributesBuilder, NotCurrentGlContext};
+    use glutin::display::GlDisplay;
+    use glutin::surface::{PbufferSurface, SurfaceAttributesBuilder};
+    use libmpv2::events::Event;
+    use libmpv2::render::mpv_render_update;
+    use libmpv2::Mpv;
+    use std::num::NonZeroU32;
+    use std::sync::mpsc;
+    use std::time::Instant;
+
+    let (Some(w), Some(h)) = (NonZeroU32::new(width), NonZeroU32::new(height)) else {
+        return Err(Error::InvalidArgument(format!(
+            "empty offscreen size {width}x{height}"
+        )));
+    };
+
+    let device = egl::device::Device::query_devices()?
+        .next()
+        .ok_or_else(|| Error::Gl("no EGL device".into()))?;
+    let display = Arc::new(glutin::display::Display::Egl(unsafe {
+        egl::display::Display::with_device(&device, None)?
+    }));
+    let template = ConfigTemplateBuilder::new()
+        .with_surface_type(ConfigSurfaceTypes::PBUFFER)
+        .build();
+    let config = unsafe { display.find_configs(template)? }
+        .next()
+        .ok_or_else(|| Error::Gl("no pbuffer-capable config".into()))?;
+    let surface_attributes = SurfaceAttributesBuilder::<PbufferSurface>::new().build(w, h);
+    let surface = unsafe { display.create_pbuffer_surface(&config, &surface_attributes)? };
+    let context_attributes = ContextAttributesBuilder::new().build(None);
+    let _context =
+        unsafe { display.create_context(&config, &context_attributes)? }.make_current(&surface)?;
+    let gl =
+        unsafe { glow::Context::from_loader_function_cstr(|name| display.get_proc_address(name)) };
+
+    let mut mpv = Mpv::with_initializer(|init| {
+        init.set_option("vo", "libmpv")?;
+        init.set_option("ao", "null")?;
+        init.set_option("pause", true)?;
+        Ok(())
+    })?;
+    let mut render_context = RenderParams::opengl(display.clone()).create(&mut mpv)?;
+    let (update_tx, update_rx) = mpsc::channel();
+    render_context.set_update_callback(move || {
+        update_tx.send(()).ok();
+    });
+    mpv.command("loadfile", &[path, "replace"])?;
+
+    let deadline = Instant::now() + FIRST_FRAME_TIMEOUT;
+    loop {
+        while let Some(event) = mpv.wait_event(0.0) {
+            match event {
+                Ok(Event::EndFile(_)) => {
+                    return Err(Error::Mpv(format!(
+                        "{path} ended before a frame was decoded"
+                    )))
+                }
+                Err(e) => return Err(e.into()),
+                Ok(_) => {}
+            }
+        }
+        if Instant::now() >= deadline {
+            return Err(Error::Mpv(format!("no frame from {path} in time")));
+        }
+        // mpv doesn't wake us for its own events, so poll them at least this often.
+        if update_rx.recv_timeout(Duration::from_millis(50)).is_ok()
+            && render_context.update()? & mpv_render_update::Frame != 0
+        {
+            break;
+        }
+    }
+
+    let target = Framebuffer::new(&gl, width as i32, height as i32)?;
+    let pixels = render_context
+        .render::<Arc<glutin::display::Display>>(target.id(), width as i32, height as i32, false)
+        .map(|()| target.read_rgba(&gl));
+    target.delete(&gl);
+
+    let mut pixels = pixels?;
+    unpremultiply(&mut pixels);
+    Ok(pixels)
+}
+
+/// mpv output is premultiplied; undo that so callers get straight alpha.
+/// Opaque video (the common case) passes through untouched.
+pub fn unpremultiply(pixels: &mut [u8]) {
+    for px in pixels.chunks_exact_mut(4) {
+        let a = px[3] as u32;
+        if a != 0 && a != 255 {
+            for c in &mut px[..3] {
+                *c = ((*c as u32 * 255 + a / 2) / a).min(255) as u8;
+            }
+        }
+    }
+}
+
+#[cfg(all(test, unix, not(target_os = "macos")))]
+mod tests {
+    use super::*;
+
+    // Run with `cargo test -- --ignored` where an EGL device is available;
+    // Mesa's llvmpipe is enough, no display server or GPU needed.
+    #[test]
+    #[ignore = "needs an EGL device"]
+    fn render_offscreen_draws_a_test_pattern() {
+        let (width, height) = (64, 48);
+        let pixels = render_offscreen("av://lavfi:testsrc", width, height).unwrap();
+        assert_eq!(pixels.len(), (width * height * 4) as usize);
+        assert!(pixels.chunks_exact(4).any(|px| px[..3] != [0, 0, 0]));
+    }
+}
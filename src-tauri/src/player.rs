@@ -0,0 +1,883 @@
+use glow::HasContext;
+use glutin::context::{PossiblyCurrentContext, PossiblyCurrentGlContext};
+use glutin::display::GetGlDisplay;
+use glutin::surface::{GlSurface, Surface, WindowSurface};
+use libmpv2::events::{mpv_event_id, Event, PropertyData};
+use libmpv2::render::{mpv_render_update, RenderContext};
+use libmpv2::{mpv_end_file_reason, Format, Mpv};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::num::NonZeroU32;
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Manager, WebviewWindow};
+use tokio::sync::oneshot;
+
+use crate::commands::audio::check_audio_device;
+use crate::commands::cache::{cancel_prebuffer, start_prebuffer};
+use crate::commands::diagnostics::CallLatency;
+use crate::commands::playback::{
+    buffered_ranges, offer_resume, parse_seekable_ranges, SeekableRange,
+};
+use crate::commands::playlist::{playlist_pos, Peek};
+use crate::commands::tracks::read_sidecar_tracks;
+use crate::commands::video::GlCaps;
+use crate::error::ErrorReport;
+use crate::file_settings::FileSettingsStore;
+use crate::mirror::Mirror;
+use crate::offscreen::{self, unpremultiply, Framebuffer};
+use crate::{Error, Result};
+
+/// Properties forwarded to the frontend under the given event name whenever
+/// mpv reports a change. The observer id is the index into this table.
+const OBSERVED_PROPERTIES: &[(&str, Format, &str)] = &[
+    ("hwdec-current", Format::String, "mpv-hwdec-current"),
+    ("current-ao", Format::String, "mpv-current-ao"),
+    ("current-edition", Format::Int64, "mpv-edition"),
+    ("paused-for-cache", Format::Flag, "mpv-paused-for-cache"),
+    (
+        "cache-buffering-state",
+        Format::Int64,
+        "mpv-cache-buffering-state",
+    ),
+    ("playlist-pos", Format::Int64, "mpv-playlist-pos"),
+    // Drives the page's "no media" screen. mpv reports the current value as
+    // soon as it's observed, which can be before the page listens, so pages
+    // should also read `idle` from `get_status` once loaded.
+    ("idle-active", Format::Flag, "mpv-idle-changed"),
+];
+
+/// Observer id for `demuxer-cache-state`, which is parsed into
+/// `mpv-seekable-ranges` rather than forwarded as-is.
+const CACHE_STATE_OBSERVER: u64 = OBSERVED_PROPERTIES.len() as u64;
+
+/// Observer id for `audio-device-list`, checked for the selected device
+/// disappearing, see `set_on_device_lost`.
+const AUDIO_DEVICES_OBSERVER: u64 = CACHE_STATE_OBSERVER + 1;
+
+/// mpv hooks handled on the render thread. The hook id is the index into
+/// this table. mpv waits for us at each one, so keep the handlers short.
+const HOOKS: &[&str] = &["on_unload", "on_load"];
+
+type PlayerCall = Box<dyn FnOnce(&mut Player) + Send>;
+
+pub enum MpvThreadEvent {
+    Redraw,
+    /// The window changed size or frame; resize the surface, then redraw.
+    Resize,
+    MpvEvents,
+    /// Work sent from a Tauri command, run with exclusive access to the player.
+    Call(PlayerCall),
+}
+
+/// Recurring work on the render thread. Returning `false` stops the timer.
+type TimerTask = Box<dyn FnMut(&mut Player) -> bool>;
+
+struct Timer {
+    name: &'static str,
+    interval: Duration,
+    next: Instant,
+    task: TimerTask,
+}
+
+/// Managed state that lets Tauri commands reach the render thread.
+#[derive(Clone)]
+pub struct PlayerHandle {
+    tx: mpsc::Sender<MpvThreadEvent>,
+}
+
+impl PlayerHandle {
+    pub fn new(tx: mpsc::Sender<MpvThreadEvent>) -> Self {
+        Self { tx }
+    }
+
+    /// Runs `f` on the render thread and blocks until it returns.
+    pub fn call<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Player) -> Result<T> + Send + 'static,
+    {
+        let (reply_tx, reply_rx) = mpsc::sync_channel(1);
+        let sent = Instant::now();
+        self.tx
+            .send(MpvThreadEvent::Call(Box::new(move |player| {
+                player.call_latency.queue.record(sent.elapsed());
+                let started = Instant::now();
+                let result = f(player);
+                player.call_latency.handling.record(started.elapsed());
+                reply_tx.send(result).ok();
+            })))
+            .map_err(|_| Error::PlayerUnavailable)?;
+        reply_rx.recv().map_err(|_| Error::PlayerUnavailable)?
+    }
+
+    /// Queues `f` for the render thread without waiting for it, for callers
+    /// on the main thread, which must never block on the render thread.
+    pub fn post(&self, f: impl FnOnce(&mut Player) + Send + 'static) -> Result<()> {
+        self.tx
+            .send(MpvThreadEvent::Call(Box::new(f)))
+            .map_err(|_| Error::PlayerUnavailable)
+    }
+}
+
+/// Waits for the render thread to send on `woken`, without holding up the
+/// async runtime. Fails with a timeout error waiting for `what` once
+/// `timeout` has passed.
+pub async fn wait_for(woken: oneshot::Receiver<()>, timeout: Duration, what: &str) -> Result<()> {
+    match tokio::time::timeout(timeout, woken).await {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(_)) => Err(Error::PlayerUnavailable),
+        Err(_) => Err(Error::Timeout(what.into())),
+    }
+}
+
+/// An owned copy of `PropertyData`, so it can outlive the event it came from.
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum PropertyValue {
+    Str(String),
+    Flag(bool),
+    Int64(i64),
+    Double(f64),
+}
+
+impl From<PropertyData<'_>> for PropertyValue {
+    fn from(data: PropertyData<'_>) -> Self {
+        match data {
+            PropertyData::Str(s) | PropertyData::OsdStr(s) => PropertyValue::Str(s.to_owned()),
+            PropertyData::Flag(b) => PropertyValue::Flag(b),
+            PropertyData::Int64(i) => PropertyValue::Int64(i),
+            PropertyData::Double(d) => PropertyValue::Double(d),
+        }
+    }
+}
+
+/// Everything owned by the render thread. Only ever touched from there, since
+/// the GL context is current on that thread alone.
+pub struct Player {
+    pub window: WebviewWindow,
+    // Declared before `mpv` so it is dropped first: the render context must
+    // be freed while the mpv handle it was created from is still alive.
+    // `None` if it couldn't be created; audio still plays then.
+    pub render_context: Option<RenderContext>,
+    pub mpv: Mpv,
+    pub surface: Surface<WindowSurface>,
+    pub context: PossiblyCurrentContext,
+    pub gl: glow::Context,
+    /// The render loop's own queue, for callbacks created later on.
+    event_tx: mpsc::Sender<MpvThreadEvent>,
+    /// Whether the stats script's overlay is toggled on.
+    pub stats_overlay: bool,
+    /// While a scrub is in progress, whether playback was paused before it
+    /// started, so `commit_seek` can put it back.
+    pub scrub_was_paused: Option<bool>,
+    /// Remembered per-file settings, or `None` if there's no app data dir.
+    pub file_settings: Option<FileSettingsStore>,
+    /// Whether the current file has finished loading (`FileLoaded` fired).
+    pub file_loaded: bool,
+    /// Playlist index of the current file, as of its `FileLoaded`.
+    pub playing_index: Option<usize>,
+    /// Whether a video frame of the current file has been presented yet.
+    pub frame_rendered: bool,
+    /// Whether each presented frame is announced with `mpv-frame`.
+    pub frame_pts_events: bool,
+    /// Woken by the next `FileLoaded`, see `wait_until_loaded`.
+    pub load_waiters: Vec<oneshot::Sender<()>>,
+    /// Woken by the next `PlaybackRestart`, see `flush_cache`.
+    pub restart_waiters: Vec<oneshot::Sender<()>>,
+    /// Where each file was left off, by path, recorded as it unloads.
+    pub playlist_positions: HashMap<String, f64>,
+    /// Second window showing the same video, see `mirror_to_window`.
+    pub mirror: Option<Mirror>,
+    /// Whether video is decoded and drawn. Off for audio-only playback.
+    pub video_enabled: bool,
+    /// The `vid` to go back to when video is turned on again.
+    pub disabled_vid: Option<String>,
+    /// Set while a playlist entry is being previewed, see `peek_next`.
+    pub peek: Option<Peek>,
+    /// What happens when the selected audio device goes away, see
+    /// `set_on_device_lost`.
+    pub on_device_lost: String,
+    /// Whether the selected audio device is missing from the device list,
+    /// so a disconnect is only handled once.
+    pub audio_device_lost: bool,
+    /// Volume to return to while `switch_audio_device_with_fade` runs.
+    pub audio_fade_volume: Option<f64>,
+    /// Share of `audio-delay` set by `set_av_delay_compensation`, in ms.
+    pub av_delay_compensation_ms: u32,
+    /// Whether a watch-later position is offered with `mpv-resume-available`
+    /// instead of resumed, see `set_resume_prompt`.
+    pub resume_prompt: bool,
+    /// Whether `load_file` plays right away unless told otherwise.
+    pub autoplay_default: bool,
+    /// Seconds `seek_step` moves per step.
+    pub seek_step: f64,
+    /// Seconds to buffer before the next file plays, see `set_prebuffer`.
+    pub prebuffer: Option<f64>,
+    /// Target and pause state to go back to while a prebuffer is holding
+    /// the current file paused, see `start_prebuffer`.
+    pub prebuffering: Option<(f64, bool)>,
+    /// Values `set_low_latency` replaced, to put back when it's turned off.
+    pub low_latency_saved: Option<Vec<(&'static str, String)>>,
+    /// The `gpu` startup option, for `get_active_gpu`.
+    pub requested_gpu: Option<String>,
+    /// The `render_to_texture` startup option.
+    pub render_to_texture: bool,
+    /// What mpv renders into with `render_to_texture`, sized to the window.
+    pub render_texture: Option<Framebuffer>,
+    /// Color the window is cleared to before each render, `None` to skip
+    /// the clear, see `set_clear_behavior`.
+    pub clear_color: Option<[f32; 4]>,
+    /// Set while the window is minimized with `set_release_gl_on_minimize`
+    /// on; nothing is drawn then.
+    pub gl_released: bool,
+    /// Whether video goes to `get_canvas_frame` instead of the window.
+    pub canvas_mode: bool,
+    /// Offscreen target of canvas mode, sized to the window.
+    pub canvas_target: Option<Framebuffer>,
+    /// Latest canvas mode frame as width, height and RGBA pixels, until
+    /// `get_canvas_frame` takes it.
+    pub canvas_frame: Option<(u32, u32, Vec<u8>)>,
+    /// Canvas frames drawn so far, sent with each `mpv-canvas-frame`.
+    canvas_frames: u64,
+    /// Filled in by the first `get_gl_caps`.
+    pub gl_caps: Option<GlCaps>,
+    /// Least time between redraws for mpv updates, see `request_redraw`.
+    pub redraw_min_interval: Duration,
+    last_redraw: Instant,
+    /// Timing of `PlayerHandle::call`s, see `get_event_latency_stats`.
+    pub call_latency: CallLatency,
+    /// Last `mpv-error` sent, so a failure repeating per frame isn't resent.
+    last_error: Option<ErrorReport>,
+    /// Last ranges sent in `mpv-seekable-ranges`.
+    seekable_ranges: Vec<SeekableRange>,
+    /// Last ranges sent in `mpv-buffered-ranges`.
+    buffered_ranges: Vec<SeekableRange>,
+    timers: Vec<Timer>,
+}
+
+impl Player {
+    pub fn new(
+        window: WebviewWindow,
+        mpv: Mpv,
+        render_context: Option<RenderContext>,
+        surface: Surface<WindowSurface>,
+        context: PossiblyCurrentContext,
+        gl: glow::Context,
+        event_tx: mpsc::Sender<MpvThreadEvent>,
+    ) -> Self {
+        let file_settings = match window.path().app_data_dir() {
+            Ok(dir) => Some(FileSettingsStore::open(dir.join("file-settings.json"))),
+            Err(e) => {
+                println!("No app data dir, per-file settings are disabled: {}", e);
+                None
+            }
+        };
+
+        Self {
+            window,
+            render_context,
+            mpv,
+            surface,
+            context,
+            gl,
+            event_tx,
+            stats_overlay: false,
+            scrub_was_paused: None,
+            file_settings,
+            file_loaded: false,
+            playing_index: None,
+            frame_rendered: false,
+            frame_pts_events: false,
+            load_waiters: Vec::new(),
+            restart_waiters: Vec::new(),
+            playlist_positions: HashMap::new(),
+            mirror: None,
+            video_enabled: true,
+            disabled_vid: None,
+            peek: None,
+            on_device_lost: "continue".into(),
+            audio_device_lost: false,
+            audio_fade_volume: None,
+            av_delay_compensation_ms: 0,
+            resume_prompt: false,
+            autoplay_default: true,
+            seek_step: 5.0,
+            prebuffer: None,
+            prebuffering: None,
+            low_latency_saved: None,
+            requested_gpu: None,
+            render_to_texture: false,
+            render_texture: None,
+            clear_color: None,
+            gl_released: false,
+            canvas_mode: false,
+            canvas_target: None,
+            canvas_frame: None,
+            canvas_frames: 0,
+            gl_caps: None,
+            redraw_min_interval: Duration::ZERO,
+            last_redraw: Instant::now(),
+            call_latency: CallLatency::default(),
+            last_error: None,
+            seekable_ranges: Vec::new(),
+            buffered_ranges: Vec::new(),
+            timers: Vec::new(),
+        }
+    }
+
+    pub fn run(mut self, event_rx: mpsc::Receiver<MpvThreadEvent>) {
+        for (id, (name, format, _)) in OBSERVED_PROPERTIES.iter().enumerate() {
+            if let Err(e) = self.mpv.observe_property(name, *format, id as u64) {
+                println!("Failed to observe {}: {}", name, e);
+            }
+        }
+        if let Err(e) =
+            self.mpv
+                .observe_property("demuxer-cache-state", Format::String, CACHE_STATE_OBSERVER)
+        {
+            println!("Failed to observe demuxer-cache-state: {}", e);
+        }
+        if let Err(e) =
+            self.mpv
+                .observe_property("audio-device-list", Format::String, AUDIO_DEVICES_OBSERVER)
+        {
+            println!("Failed to observe audio-device-list: {}", e);
+        }
+
+        for (id, name) in HOOKS.iter().enumerate() {
+            let name = CString::new(*name).unwrap();
+            let err = unsafe {
+                libmpv2_sys::mpv_hook_add(self.mpv.ctx.as_ptr(), id as u64, name.as_ptr(), 0)
+            };
+            if err < 0 {
+                println!("Failed to add {:?} hook: {}", name, err);
+            }
+        }
+
+        loop {
+            let event = match self.next_timer_deadline() {
+                Some(deadline) => {
+                    let timeout = deadline.saturating_duration_since(Instant::now());
+                    match event_rx.recv_timeout(timeout) {
+                        Ok(event) => Some(event),
+                        Err(mpsc::RecvTimeoutError::Timeout) => None,
+                        Err(mpsc::RecvTimeoutError::Disconnected) => return,
+                    }
+                }
+                None => match event_rx.recv() {
+                    Ok(event) => Some(event),
+                    Err(_) => return,
+                },
+            };
+
+            if let Some(event) = event {
+                match event {
+                    MpvThreadEvent::Redraw => self.request_redraw(),
+                    MpvThreadEvent::Resize => self.resize(),
+                    MpvThreadEvent::MpvEvents => {
+                        if !self.handle_mpv_events() {
+                            return;
+                        }
+                    }
+                    MpvThreadEvent::Call(call) => call(&mut self),
+                }
+            }
+            self.run_due_timers();
+        }
+    }
+
+    /// Runs `task` every `interval` until it returns `false` or is cancelled,
+    /// replacing any timer already running under `name`.
+    pub fn set_timer(
+        &mut self,
+        name: &'static str,
+        interval: Duration,
+        task: impl FnMut(&mut Player) -> bool + 'static,
+    ) {
+        self.cancel_timer(name);
+        self.timers.push(Timer {
+            name,
+            interval,
+            next: Instant::now() + interval,
+            task: Box::new(task),
+        });
+    }
+
+    pub fn cancel_timer(&mut self, name: &str) {
+        self.timers.retain(|timer| timer.name != name);
+    }
+
+    fn next_timer_deadline(&self) -> Option<Instant> {
+        self.timers.iter().map(|timer| timer.next).min()
+    }
+
+    fn run_due_timers(&mut self) {
+        let now = Instant::now();
+        let mut timers = std::mem::take(&mut self.timers);
+        timers.retain_mut(|timer| {
+            if timer.next > now {
+                return true;
+            }
+            timer.next = now + timer.interval;
+            (timer.task)(self)
+        });
+        // Tasks may have rescheduled timers under the same name meanwhile;
+        // the newer ones win.
+        timers.retain(|timer| !self.timers.iter().any(|new| new.name == timer.name));
+        self.timers.extend(timers);
+    }
+
+    /// Matches the surface to the window's current size and redraws, so the
+    /// last frame is shown at the new size straight away. Some platforms
+    /// (Wayland in particular) leave the old buffer size in place otherwise.
+    pub fn resize(&mut self) {
+        // Kept at 1x1 until `restore_gl`, which resizes then.
+        if self.gl_released {
+            return;
+        }
+        let size = self.window.inner_size().ok();
+        if let Some((Some(width), Some(height))) =
+            size.map(|size| (NonZeroU32::new(size.width), NonZeroU32::new(size.height)))
+        {
+            self.surface.resize(&self.context, width, height);
+        }
+        self.redraw();
+    }
+
+    /// Redraws for an mpv update, at most once per `redraw_min_interval`.
+    /// Updates coming in faster are folded into one redraw at the end of the
+    /// interval, which then renders whatever frame is newest.
+    fn request_redraw(&mut self) {
+        let since = self.last_redraw.elapsed();
+        if since >= self.redraw_min_interval {
+            self.redraw();
+        } else if !self.timers.iter().any(|timer| timer.name == "redraw") {
+            self.set_timer("redraw", self.redraw_min_interval - since, |player| {
+                player.redraw();
+                false
+            });
+        }
+    }
+
+    /// The render context, or an error if it couldn't be created.
+    pub fn render_context(&self) -> Result<&RenderContext> {
+        self.render_context
+            .as_ref()
+            .ok_or_else(|| Error::Gl("the render context is unavailable".into()))
+    }
+
+    /// Tries to create the render context again after it failed at startup,
+    /// e.g. once the graphics driver has been fixed. Returns whether a new
+    /// one was created; `false` if there already was one.
+    pub fn retry_render(&mut self) -> Result<bool> {
+        if self.render_context.is_some() {
+            return Ok(false);
+        }
+        let display = Arc::new(self.context.display());
+        let render_context =
+            crate::create_render_context(&mut self.mpv, display, self.event_tx.clone())?;
+        self.render_context = Some(render_context);
+        self.redraw();
+        Ok(true)
+    }
+
+    /// Frees what the GPU holds for drawing while the window is minimized:
+    /// the offscreen targets are deleted, the surface is shrunk to 1x1 and
+    /// drawing stops until `restore_gl`. The surface itself stays, and with
+    /// it the current context, since mpv can still run GL work from
+    /// `update()` at any time. Emits `mpv-gl-released`.
+    pub fn release_gl(&mut self) {
+        if self.gl_released {
+            return;
+        }
+        self.gl_released = true;
+        self.cancel_timer("redraw");
+        if let Some(target) = self.render_texture.take() {
+            target.delete(&self.gl);
+        }
+        if let Some(target) = self.canvas_target.take() {
+            target.delete(&self.gl);
+        }
+        self.canvas_frame = None;
+        self.surface
+            .resize(&self.context, NonZeroU32::MIN, NonZeroU32::MIN);
+        unsafe { self.gl.finish() };
+        self.emit("mpv-gl-released", ());
+    }
+
+    /// Undoes `release_gl`: sizes the surface to the window again and
+    /// redraws, recreating the offscreen targets as needed. Emits
+    /// `mpv-gl-restored`.
+    pub fn restore_gl(&mut self) {
+        if !self.gl_released {
+            return;
+        }
+        self.gl_released = false;
+        self.resize();
+        self.emit("mpv-gl-restored", ());
+    }
+
+    /// Draws and presents a frame right away, skipping the wait that
+    /// `redraw_min_interval` may impose, then waits for the GPU to finish
+    /// it. Fails if there's no render context, video is off, or the draw
+    /// failed.
+    pub fn render_once(&mut self) -> Result<()> {
+        self.render_context()?;
+        if !self.video_enabled {
+            return Err(Error::InvalidArgument("video is turned off".into()));
+        }
+        // This draw covers whatever the pending one would have shown.
+        self.cancel_timer("redraw");
+        if !self.redraw() {
+            return Err(match &self.last_error {
+                Some(report) => Error::Gl(report.message.clone()),
+                None => Error::Window("the window can't be drawn to".into()),
+            });
+        }
+        unsafe { self.gl.finish() };
+        Ok(())
+    }
+
+    /// Draws the newest frame and presents it. Returns whether that worked.
+    pub fn redraw(&mut self) -> bool {
+        self.last_redraw = Instant::now();
+        let Some(render_context) = &self.render_context else {
+            self.present_clear();
+            return false;
+        };
+        // Runs work mpv has queued for this thread, like GPU screenshots.
+        // mpv expects this after every update callback.
+        let new_frame = match render_context.update() {
+            Ok(flags) => flags & mpv_render_update::Frame != 0,
+            Err(e) => {
+                println!("Failed to update render context: {}", e);
+                false
+            }
+        };
+
+        if self.gl_released {
+            return false;
+        }
+
+        // Nothing is decoded with video off, so leave the GPU idle.
+        if !self.video_enabled {
+            self.present_clear();
+            return false;
+        }
+
+        // Fails while the window is being torn down; nothing to draw then.
+        let Ok(size) = self.window.inner_size() else {
+            return false;
+        };
+        // println!("Redrawing frame at size: {}x{}", size.width, size.height);
+
+        let (width, height) = (size.width as i32, size.height as i32);
+        let presented = if self.canvas_mode {
+            self.draw_canvas(width, height)
+        } else {
+            self.draw_window(width, height)
+        };
+        if !presented {
+            return false;
+        }
+        self.last_error = None;
+        if new_frame && self.file_loaded {
+            self.frame_rendered = true;
+            if self.frame_pts_events {
+                if let Ok(pts) = self.mpv.get_property::<f64>("time-pos") {
+                    self.emit("mpv-frame", pts);
+                }
+            }
+        }
+
+        if let (Some(mirror), Some(render_context)) = (&self.mirror, &self.render_context) {
+            if let Err(e) = mirror.draw(render_context, &self.context) {
+                println!("Failed to draw mirror: {}", e);
+            }
+            if let Err(e) = self.context.make_current(&self.surface) {
+                self.report_error("Failed to make context current", e.into());
+            }
+        }
+        true
+    }
+
+    /// Renders into the window (through the render texture, if enabled) and
+    /// presents it. Returns whether that worked.
+    fn draw_window(&mut self, width: i32, height: i32) -> bool {
+        let fbo = match self.render_texture_fbo(width, height) {
+            Ok(fbo) => fbo,
+            Err(e) => {
+                self.report_error("Failed to create render texture", e);
+                return false;
+            }
+        };
+        if let Some(color) = self.clear_color {
+            offscreen::clear(&self.gl, self.render_texture.as_ref(), color);
+        }
+        let rendered = self.render_context().and_then(|render_context| {
+            Ok(render_context.render::<Arc<glutin::display::Display>>(fbo, width, height, true)?)
+        });
+        if let Err(e) = rendered {
+            self.report_error("Failed to draw video frame", e);
+            return false;
+        }
+        if let Some(texture) = &self.render_texture {
+            texture.blit_to_default(&self.gl);
+        }
+        if let Err(e) = self.surface.swap_buffers(&self.context) {
+            self.report_error("Failed to swap buffers", e.into());
+            return false;
+        }
+        // Feeds mpv's vsync timing, which `estimated-display-fps` is based on.
+        if let Some(render_context) = &self.render_context {
+            render_context.report_swap();
+        }
+        true
+    }
+
+    /// Shows just the clear color in the window when there's no video to
+    /// draw, if `set_clear_behavior` turned clearing on.
+    fn present_clear(&mut self) {
+        let Some(color) = self.clear_color else {
+            return;
+        };
+        if self.canvas_mode {
+            return;
+        }
+        offscreen::clear(&self.gl, None, color);
+        if let Err(e) = self.surface.swap_buffers(&self.context) {
+            self.report_error("Failed to swap buffers", e.into());
+        }
+    }
+
+    /// Canvas mode: renders offscreen instead of into the window and keeps
+    /// the pixels for `get_canvas_frame`, announcing them with
+    /// `mpv-canvas-frame`. Returns whether that worked.
+    fn draw_canvas(&mut self, width: i32, height: i32) -> bool {
+        let target = match Framebuffer::ensure(&self.gl, &mut self.canvas_target, width, height) {
+            Ok(target) => target,
+            Err(e) => {
+                self.report_error("Failed to create canvas framebuffer", e);
+                return false;
+            }
+        };
+        // Unflipped, so the readback comes out top row first, as a canvas
+        // wants it.
+        // `redraw` only gets here with a render context.
+        let Some(render_context) = &self.render_context else {
+            return false;
+        };
+        let pixels = render_context
+            .render::<Arc<glutin::display::Display>>(target.id(), width, height, false)
+            .map(|()| target.read_rgba(&self.gl));
+        let mut pixels = match pixels {
+            Ok(pixels) => pixels,
+            Err(e) => {
+                self.report_error("Failed to draw video frame", e.into());
+                return false;
+            }
+        };
+        unpremultiply(&mut pixels);
+        self.canvas_frame = Some((width as u32, height as u32, pixels));
+        self.canvas_frames += 1;
+        self.emit("mpv-canvas-frame", self.canvas_frames);
+        true
+    }
+
+    /// Drains mpv's event queue. Returns `false` once the render thread should exit.
+    fn handle_mpv_events(&mut self) -> bool {
+        while let Some(mpv_event) = self.mpv.wait_event(0.0) {
+            match mpv_event {
+                // Moving on to another file ends the current one too, so
+                // only a quit takes the render thread down.
+                Ok(Event::EndFile(reason)) if reason == mpv_end_file_reason::Quit => {
+                    println!("mpv quit. Exiting render thread.");
+                    return false;
+                }
+                // libmpv2 drops the error code, so all that's known is that
+                // mpv gave up on the file.
+                Ok(Event::EndFile(reason)) if reason == mpv_end_file_reason::Error => {
+                    cancel_prebuffer(self);
+                    self.report_error(
+                        "Playback stopped",
+                        Error::Mpv("the file couldn't be opened or decoded".into()),
+                    );
+                }
+                Ok(Event::EndFile(reason)) if reason == mpv_end_file_reason::Eof => {
+                    cancel_prebuffer(self);
+                    self.file_ended();
+                }
+                Ok(Event::EndFile(_)) => cancel_prebuffer(self),
+                Ok(Event::PropertyChange {
+                    change: PropertyData::Str(json),
+                    reply_userdata: CACHE_STATE_OBSERVER,
+                    ..
+                }) => {
+                    if let Ok(state) = serde_json::from_str(json) {
+                        self.update_seekable_ranges(parse_seekable_ranges(&state));
+                        let buffered = buffered_ranges(self, &state);
+                        if buffered != self.buffered_ranges {
+                            self.emit("mpv-buffered-ranges", &buffered);
+                            self.buffered_ranges = buffered;
+                        }
+                    }
+                }
+                Ok(Event::PropertyChange {
+                    change: PropertyData::Str(json),
+                    reply_userdata: AUDIO_DEVICES_OBSERVER,
+                    ..
+                }) => {
+                    if let Ok(devices) = serde_json::from_str(json) {
+                        check_audio_device(self, &devices);
+                    }
+                }
+                Ok(Event::PropertyChange {
+                    change,
+                    reply_userdata,
+                    ..
+                }) => {
+                    let value = PropertyValue::from(change);
+                    if let Some((_, _, event)) = OBSERVED_PROPERTIES.get(reply_userdata as usize) {
+                        self.emit(event, value);
+                    }
+                }
+                Ok(Event::StartFile) => {
+                    self.file_loaded = false;
+                    self.frame_rendered = false;
+                    cancel_prebuffer(self);
+                    start_prebuffer(self);
+                }
+                Ok(Event::PlaybackRestart) => {
+                    for waiter in self.restart_waiters.drain(..) {
+                        waiter.send(()).ok();
+                    }
+                }
+                Ok(Event::FileLoaded) => {
+                    self.file_loaded = true;
+                    self.frame_rendered = false;
+                    self.playing_index = playlist_pos(self);
+                    self.apply_file_settings();
+                    self.emit("mpv-sidecar-tracks", read_sidecar_tracks(self));
+                    for waiter in self.load_waiters.drain(..) {
+                        waiter.send(()).ok();
+                    }
+                }
+                Ok(Event::Deprecated(raw)) if raw.event_id == mpv_event_id::Hook => {
+                    let hook = unsafe { *(raw.data as *const libmpv2_sys::mpv_event_hook) };
+                    self.run_hook(raw.reply_userdata);
+                    unsafe { libmpv2_sys::mpv_hook_continue(self.mpv.ctx.as_ptr(), hook.id) };
+                }
+                Ok(e) => {
+                    println!("Received MPV Event: {:?}", e);
+                }
+                // A file that fails to load (a dead playlist entry, say)
+                // arrives as an error too. mpv moves on by itself.
+                Err(e) => {
+                    println!("MPV event error: {}", e);
+                }
+            }
+        }
+        true
+    }
+
+    fn update_seekable_ranges(&mut self, ranges: Vec<SeekableRange>) {
+        if ranges != self.seekable_ranges {
+            self.emit("mpv-seekable-ranges", &ranges);
+            self.seekable_ranges = ranges;
+        }
+    }
+
+    /// Records where the unloading file was left, or forgets it if it played
+    /// to the end, so a restored playlist doesn't resume at the credits.
+    fn remember_position(&mut self) {
+        let Ok(path) = self.mpv.get_property::<String>("path") else {
+            return;
+        };
+        if path.starts_with("hex://") {
+            return;
+        }
+        let finished = self.mpv.get_property("eof-reached").unwrap_or(false);
+        match self.mpv.get_property::<f64>("time-pos") {
+            Ok(position) if !finished => {
+                self.playlist_positions.insert(path, position);
+            }
+            _ => {
+                self.playlist_positions.remove(&path);
+            }
+        }
+    }
+
+    fn run_hook(&mut self, id: u64) {
+        match HOOKS.get(id as usize) {
+            Some(&"on_unload") => {
+                if let Err(e) = self.save_file_settings() {
+                    println!("Failed to save file settings: {}", e);
+                }
+                self.remember_position();
+            }
+            Some(&"on_load") if self.resume_prompt => offer_resume(self),
+            _ => {}
+        }
+    }
+
+    /// Reads a node-valued property (`track-list`, `demuxer-cache-state`, ...).
+    /// mpv formats nodes as JSON when asked for a string.
+    pub fn get_json(&self, name: &str) -> Result<serde_json::Value> {
+        let json: String = self.mpv.get_property(name)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    /// The framebuffer to render into: 0 for the window, or with
+    /// `render_to_texture` set, the render texture, (re)created to match the
+    /// window size.
+    fn render_texture_fbo(&mut self, width: i32, height: i32) -> Result<i32> {
+        if !self.render_to_texture {
+            return Ok(0);
+        }
+        Ok(Framebuffer::ensure(&self.gl, &mut self.render_texture, width, height)?.id())
+    }
+
+    /// A file played to its end: emits `mpv-playlist-finished` with the
+    /// entry count if it was the last entry and the playlist doesn't loop
+    /// (a single loaded file counts as a playlist of one), and `mpv-ended`
+    /// with its index otherwise. The index is the one recorded at load, since
+    /// mpv may already have moved on by the time the event is handled.
+    fn file_ended(&mut self) {
+        let count = self
+            .mpv
+            .get_property::<i64>("playlist-count")
+            .unwrap_or(0)
+            .max(0) as usize;
+        let looping = self
+            .mpv
+            .get_property::<String>("loop-playlist")
+            .is_ok_and(|value| value != "no");
+        let last = self.playing_index.is_none_or(|index| index + 1 >= count);
+        if last && !looping {
+            self.emit("mpv-playlist-finished", count);
+        } else {
+            self.emit("mpv-ended", self.playing_index);
+        }
+    }
+
+    /// Logs `err` and sends it to the frontend as `mpv-error`, keeping the
+    /// player running. A failure that repeats every frame is only sent once,
+    /// until a frame gets through again.
+    pub fn report_error(&mut self, context: &str, err: Error) {
+        let report = ErrorReport::new(context, &err);
+        if self.last_error.as_ref() == Some(&report) {
+            return;
+        }
+        println!("{}", report.message);
+        self.emit("mpv-error", report.clone());
+        self.last_error = Some(report);
+    }
+
+    pub fn emit<S: Serialize + Clone>(&self, event: &str, payload: S) {
+        if let Err(e) = self.window.emit(event, payload) {
+            println!("Failed to emit {}: {}", event, e);
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use libmpv2::events::Event;
+use libmpv2::Mpv;
+use serde::Serialize;
+use std::collections::HashSet;
+
+use crate::frame_dump::wait_for;
+use crate::Result;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamReport {
+    pub id: i64,
+    /// `video`, `audio` or `sub`.
+    pub kind: String,
+    /// mpv's codec name, e.g. `h264` or `opus`.
+    pub codec: String,
+    /// Whether this mpv build has a decoder for the codec.
+    pub decodable: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodeReport {
+    /// Container format, e.g. `mov,mp4,m4a,3gp,3g2,mj2`.
+    pub format: Option<String>,
+    pub streams: Vec<StreamReport>,
+    /// Whether at least one video or audio stream can be decoded.
+    pub playable: bool,
+}
+
+fn get_json(mpv: &Mpv, name: &str) -> Result<serde_json::Value> {
+    let json: String = mpv.get_property(name)?;
+    Ok(serde_json::from_str(&json)?)
+}
+
+/// Opens `path` in a throwaway mpv with every track deselected, so nothing
+/// is decoded, and checks each stream's codec against mpv's `decoder-list`.
+/// Only the demuxer runs, which usually takes well under a second; failing
+/// to open the file at all is an error.
+pub fn probe(path: &str) -> Result<DecodeReport> {
+    let mut mpv = Mpv::with_initializer(|init| {
+        init.set_option("vo", "null")?;
+        init.set_option("ao", "null")?;
+        init.set_option("pause", true)?;
+        init.set_option("vid", "no")?;
+        init.set_option("aid", "no")?;
+        init.set_option("sid", "no")?;
+        Ok(())
+    })?;
+    mpv.command("loadfile", &[path, "replace"])?;
+    wait_for(&mut mpv, "the file to open", |event| {
+        matches!(event, Event::FileLoaded)
+    })?;
+
+    let decoders: HashSet<String> = get_json(&mpv, "decoder-list")?
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|decoder| decoder["codec"].as_str().map(str::to_owned))
+        .collect();
+    let streams: Vec<StreamReport> = get_json(&mpv, "track-list")?
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|track| {
+            let codec = track["codec"].as_str()?.to_owned();
+            Some(StreamReport {
+                id: track["id"].as_i64()?,
+                kind: track["type"].as_str()?.to_owned(),
+                decodable: decoders.contains(&codec),
+                codec,
+            })
+        })
+        .collect();
+    let playable = streams
+        .iter()
+        .any(|stream| stream.decodable && stream.kind != "sub");
+
+    Ok(DecodeReport {
+        format: mpv.get_property("file-format").ok(),
+        streams,
+        playable,
+    })
+}
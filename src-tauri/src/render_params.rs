@@ -0,0 +1,166 @@
+use libmpv2::render::{OpenGLInitParams, RenderContext, RenderParam, RenderParamApiType};
+use libmpv2::Mpv;
+use std::sync::Arc;
+
+use crate::{Error, Result};
+
+/// Collects the parameters `RenderContext::new` is called with, checking
+/// them before mpv sees them. mpv only reads some `RenderParam`s when the
+/// context is created; the rest belong to `render()` or
+/// `set_parameter()`, and passing them here would at best be ignored. Those
+/// are logged and left out instead of failing, so a param added for one
+/// libmpv version doesn't keep rendering from starting on another.
+pub struct RenderParams<C> {
+    api_type: Option<RenderParamApiType>,
+    init_params: Option<OpenGLInitParams<C>>,
+    /// Other creation-time params, in the order they were added.
+    extra: Vec<RenderParam<C>>,
+}
+
+impl RenderParams<Arc<glutin::display::Display>> {
+    /// OpenGL through `display`'s current context: what the player and
+    /// `render_offscreen` use.
+    pub fn opengl(display: Arc<glutin::display::Display>) -> Self {
+        Self::new()
+            .param(RenderParam::ApiType(RenderParamApiType::OpenGl))
+            .param(RenderParam::InitParams(OpenGLInitParams {
+                get_proc_address: crate::get_proc_address,
+                ctx: display,
+            }))
+    }
+}
+
+impl<C> RenderParams<C> {
+    pub fn new() -> Self {
+        Self {
+            api_type: None,
+            init_params: None,
+            extra: Vec::new(),
+        }
+    }
+
+    /// Adds `param`. A second API type or set of init params replaces the
+    /// first; params mpv doesn't take at creation are dropped with a log line.
+    pub fn param(mut self, param: RenderParam<C>) -> Self {
+        match param {
+            RenderParam::ApiType(api_type) => {
+                if self.api_type.replace(api_type).is_some() {
+                    println!("Render API type given twice, using the last one");
+                }
+            }
+            RenderParam::InitParams(init_params) => {
+                if self.init_params.replace(init_params).is_some() {
+                    println!("Render init params given twice, using the last ones");
+                }
+            }
+            param @ (RenderParam::X11Display(_)
+            | RenderParam::WaylandDisplay(_)
+            | RenderParam::AdvancedControl(_)) => self.extra.push(param),
+            param => {
+                println!(
+                    "Ignoring render param {} at context creation",
+                    u32::from(&param)
+                );
+            }
+        }
+        self
+    }
+
+    /// The params in the order mpv expects, API type first. Fails if
+    /// something the API type needs is missing.
+    pub fn build(self) -> Result<Vec<RenderParam<C>>> {
+        let api_type = self
+            .api_type
+            .ok_or_else(|| Error::Gl("no render API type given".into()))?;
+        let mut params = Vec::with_capacity(self.extra.len() + 2);
+        match api_type {
+            RenderParamApiType::OpenGl => {
+                let init_params = self
+                    .init_params
+                    .ok_or_else(|| Error::Gl("OpenGL rendering needs init params".into()))?;
+                params.push(RenderParam::ApiType(RenderParamApiType::OpenGl));
+                params.push(RenderParam::InitParams(init_params));
+            }
+        }
+        params.extend(self.extra);
+        Ok(params)
+    }
+
+    /// Builds the params and creates the render context from them.
+    pub fn create(self, mpv: &mut Mpv) -> Result<RenderContext> {
+        let params = self.build()?;
+        Ok(RenderContext::new(crate::mpv_handle(mpv), params)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::c_void;
+
+    fn no_proc_address(_ctx: &(), _name: &str) -> *mut c_void {
+        std::ptr::null_mut()
+    }
+
+    fn init_params() -> RenderParam<()> {
+        RenderParam::InitParams(OpenGLInitParams {
+            get_proc_address: no_proc_address,
+            ctx: (),
+        })
+    }
+
+    fn ids(params: &[RenderParam<()>]) -> Vec<u32> {
+        params.iter().map(u32::from).collect()
+    }
+
+    #[test]
+    fn build_needs_an_api_type() {
+        let err = RenderParams::new().param(init_params()).build().err();
+        assert!(matches!(err, Some(Error::Gl(msg)) if msg == "no render API type given"));
+    }
+
+    #[test]
+    fn opengl_needs_init_params() {
+        let err = RenderParams::<()>::new()
+            .param(RenderParam::ApiType(RenderParamApiType::OpenGl))
+            .build()
+            .err();
+        assert!(matches!(err, Some(Error::Gl(msg)) if msg == "OpenGL rendering needs init params"));
+    }
+
+    #[test]
+    fn build_puts_api_type_first() {
+        let params = RenderParams::new()
+            .param(RenderParam::AdvancedControl(true))
+            .param(init_params())
+            .param(RenderParam::ApiType(RenderParamApiType::OpenGl))
+            .build()
+            .unwrap();
+        assert_eq!(ids(&params), [1, 2, 10]);
+    }
+
+    #[test]
+    fn duplicates_keep_the_last_one() {
+        let params = RenderParams::new()
+            .param(RenderParam::ApiType(RenderParamApiType::OpenGl))
+            .param(RenderParam::ApiType(RenderParamApiType::OpenGl))
+            .param(init_params())
+            .param(init_params())
+            .build()
+            .unwrap();
+        assert_eq!(ids(&params), [1, 2]);
+    }
+
+    #[test]
+    fn non_creation_params_are_dropped() {
+        let params = RenderParams::new()
+            .param(RenderParam::ApiType(RenderParamApiType::OpenGl))
+            .param(init_params())
+            .param(RenderParam::FlipY(true))
+            .param(RenderParam::Depth(8))
+            .param(RenderParam::AdvancedControl(false))
+            .build()
+            .unwrap();
+        assert_eq!(ids(&params), [1, 2, 10]);
+    }
+}
@@ -0,0 +1,174 @@
+use libmpv2::events::Event;
+use libmpv2::{mpv_end_file_reason, Mpv};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::frame_dump::wait_for;
+use crate::{Error, Result};
+
+/// Accepted bucket counts for `generate_waveform`.
+const BUCKET_RANGE: std::ops::RangeInclusive<u32> = 1..=100_000;
+
+/// Rate the audio is resampled to before taking peaks. High enough for the
+/// envelope a scrubber shows, low enough that a feature film's samples fit
+/// in ~30 MB.
+const SAMPLE_RATE: u32 = 2000;
+
+/// Longest a waveform may take to decode before it's given up on.
+const DECODE_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// How often progress is reported.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+static NEXT_WAVEFORM_CALL: AtomicU32 = AtomicU32::new(0);
+
+/// Finished waveforms by path and bucket count.
+type WaveformCache = HashMap<(String, u32), Vec<f32>>;
+
+static WAVEFORMS: Mutex<Option<WaveformCache>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WaveformProgress {
+    /// Seconds of audio decoded so far.
+    pub position: f64,
+    pub duration: Option<f64>,
+}
+
+/// Peak amplitude of each of `buckets` equal slices of `samples`, 0 to 1.
+fn peaks(samples: &[i16], buckets: u32) -> Vec<f32> {
+    if samples.is_empty() {
+        return Vec::new();
+    }
+    let buckets = buckets as usize;
+    (0..buckets)
+        .map(|bucket| {
+            let start = bucket * samples.len() / buckets;
+            let end = ((bucket + 1) * samples.len() / buckets).max(start + 1);
+            let peak = samples[start..end.min(samples.len())]
+                .iter()
+                .map(|sample| sample.unsigned_abs())
+                .max()
+                .unwrap_or(0);
+            peak as f32 / 32768.0
+        })
+        .collect()
+}
+
+/// Decodes the first audio track of `path` to mono at a low rate in a
+/// throwaway mpv (audio written raw by `ao=pcm` to a temp file), calling
+/// `progress` a few times a second, and returns the peak amplitude of each
+/// of `buckets` equal slices of it, 0 to 1. Files without audio give an
+/// empty list.
+///
+/// The player isn't disturbed. Results are kept per path and bucket count
+/// for the rest of the session, so asking again is free. Call it off the
+/// render thread; it blocks until the whole file is decoded, which takes a
+/// few seconds per hour of audio.
+pub fn generate_waveform(
+    path: &str,
+    buckets: u32,
+    mut progress: impl FnMut(WaveformProgress),
+) -> Result<Vec<f32>> {
+    if !BUCKET_RANGE.contains(&buckets) {
+        return Err(Error::InvalidArgument(format!(
+            "{buckets} waveform buckets is outside 1..=100000"
+        )));
+    }
+    let key = (path.to_owned(), buckets);
+    if let Some(peaks) = WAVEFORMS
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|cache| cache.get(&key))
+    {
+        return Ok(peaks.clone());
+    }
+
+    // Unique per call, in case two run at once.
+    let call = NEXT_WAVEFORM_CALL.fetch_add(1, Ordering::Relaxed);
+    let file = std::env::temp_dir().join(format!("mpv-waveform-{}-{call}.pcm", std::process::id()));
+    let file_str = file
+        .to_str()
+        .ok_or_else(|| Error::InvalidArgument(format!("{} isn't UTF-8", file.display())))?;
+
+    let decoded = decode(path, file_str, &mut progress);
+    let samples = fs::read(&file);
+    fs::remove_file(&file).ok();
+    if !decoded? {
+        return Ok(Vec::new());
+    }
+
+    let samples: Vec<i16> = samples?
+        .chunks_exact(2)
+        .map(|sample| i16::from_le_bytes([sample[0], sample[1]]))
+        .collect();
+    let peaks = peaks(&samples, buckets);
+    WAVEFORMS
+        .lock()
+        .unwrap()
+        .get_or_insert_with(HashMap::new)
+        .insert(key, peaks.clone());
+    Ok(peaks)
+}
+
+/// Plays `path`'s audio into `out` as raw 16-bit mono. Returns `false`
+/// without writing anything if it has no audio.
+fn decode(path: &str, out: &str, progress: &mut impl FnMut(WaveformProgress)) -> Result<bool> {
+    let mut mpv = Mpv::with_initializer(|init| {
+        init.set_option("vo", "null")?;
+        init.set_option("vid", "no")?;
+        init.set_option("sid", "no")?;
+        init.set_option("ao", "pcm")?;
+        init.set_option("ao-pcm-file", out)?;
+        init.set_option("ao-pcm-waveheader", false)?;
+        init.set_option("audio-format", "s16")?;
+        init.set_option("audio-channels", "mono")?;
+        init.set_option("audio-samplerate", SAMPLE_RATE as i64)?;
+        // Replay gain and normalization would distort the peaks.
+        init.set_option("replaygain", "no")?;
+        init.set_option("volume", 100)?;
+        Ok(())
+    })?;
+    mpv.command("loadfile", &[path, "replace"])?;
+    wait_for(&mut mpv, "the file to open", |event| {
+        matches!(event, Event::FileLoaded)
+    })?;
+    // `aid` stays `auto` either way; only the selected track tells.
+    if mpv.get_property::<i64>("current-tracks/audio/id").is_err() {
+        return Ok(false);
+    }
+
+    let duration: Option<f64> = mpv.get_property("duration").ok();
+    let started = Instant::now();
+    let mut reported = started;
+    loop {
+        if started.elapsed() >= DECODE_TIMEOUT {
+            return Err(Error::Timeout("the waveform to decode".into()));
+        }
+        match mpv.wait_event(PROGRESS_INTERVAL.as_secs_f64()) {
+            Some(Ok(Event::EndFile(reason))) if reason == mpv_end_file_reason::Eof => break,
+            Some(Ok(Event::EndFile(_))) => {
+                return Err(Error::Mpv("the file couldn't be decoded".into()))
+            }
+            Some(Err(e)) => return Err(e.into()),
+            _ => {}
+        }
+        if reported.elapsed() >= PROGRESS_INTERVAL {
+            reported = Instant::now();
+            let position = mpv.get_property("time-pos").unwrap_or(0.0);
+            progress(WaveformProgress { position, duration });
+        }
+    }
+    // Destroying mpv closes the audio output, which flushes the file.
+    drop(mpv);
+    progress(WaveformProgress {
+        position: duration.unwrap_or(0.0),
+        duration,
+    });
+    Ok(true)
+}